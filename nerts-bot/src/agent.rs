@@ -0,0 +1,645 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use log::{debug, error, info};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use tokio::{sync::watch, time::Instant};
+
+use crate::{
+    difficulty::DifficultySettings,
+    events::{GameEvent, RaceRecovery},
+    history::{MatchHistory, PlayerResult, RoundRecord},
+    input::Action,
+    messages::server::GamePhase,
+    position::Position,
+    state::{
+        card::{Card, CardData},
+        stack::PlayedStack,
+        GameState, STACKED_CARDS_Y_OFFSET,
+    },
+    strategy::{held_fits, Decision, Source, Strategy, Target, CONTESTED_CONFIDENCE},
+    Bot, BotHandle,
+};
+
+/// Plays the game by asking a [Strategy] what to do and carrying it out
+///
+/// The [DifficultySettings] are applied here: the reaction delay before each decision, mistakes,
+/// when nerts is called and the cursor motion profile.
+pub struct Agent {
+    /// Shared with the blocking task each decision is made on
+    strategy: Arc<Mutex<Box<dyn Strategy>>>,
+    difficulty: DifficultySettings,
+    rng: StdRng,
+    /// When nerts could first be called
+    nerts_possible_since: Option<Instant>,
+    /// Where finished rounds are recorded
+    history: Option<MatchHistory>,
+    round: Option<RoundInProgress>,
+    control: AgentControl,
+}
+
+/// Pauses, resumes or switches the strategy of an [Agent] from another task while it's running
+///
+/// Changes take effect before the agent's next decision, so anything already being carried out is
+/// finished first.
+#[derive(Clone)]
+pub struct AgentControl {
+    paused: Arc<watch::Sender<bool>>,
+    /// Strategy to switch to before the next decision
+    next_strategy: Arc<Mutex<Option<Box<dyn Strategy>>>>,
+    strategy_name: Arc<Mutex<&'static str>>,
+}
+
+impl AgentControl {
+    fn new(strategy_name: &'static str) -> Self {
+        Self {
+            paused: Arc::new(watch::channel(false).0),
+            next_strategy: Arc::default(),
+            strategy_name: Arc::new(Mutex::new(strategy_name)),
+        }
+    }
+
+    /// Stops making decisions until resumed
+    pub fn pause(&self) {
+        self.paused.send_replace(true);
+    }
+
+    pub fn resume(&self) {
+        self.paused.send_replace(false);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.paused.borrow()
+    }
+
+    pub fn set_strategy(&self, strategy: Box<dyn Strategy>) {
+        *self.next_strategy.lock().unwrap() = Some(strategy);
+    }
+
+    /// Name of the strategy being played with, or about to be if it's just been switched
+    pub fn strategy_name(&self) -> &'static str {
+        match self.next_strategy.lock().unwrap().as_ref() {
+            Some(strategy) => strategy.name(),
+            None => *self.strategy_name.lock().unwrap(),
+        }
+    }
+
+    async fn wait_until_resumed(&self) {
+        let mut paused = self.paused.subscribe();
+        let _ = paused.wait_for(|paused| !paused).await;
+    }
+}
+
+/// What's needed to record the current round once it's over
+#[derive(Debug, Clone, Copy)]
+struct RoundInProgress {
+    started: Instant,
+    rounds_before: usize,
+    plays: u32,
+}
+
+impl Agent {
+    pub fn new(strategy: Box<dyn Strategy>, difficulty: DifficultySettings) -> Self {
+        Self {
+            control: AgentControl::new(strategy.name()),
+            strategy: Arc::new(Mutex::new(strategy)),
+            difficulty,
+            rng: StdRng::from_entropy(),
+            nerts_possible_since: None,
+            history: None,
+            round: None,
+        }
+    }
+
+    /// Records every round finished from now on
+    pub fn set_history(&mut self, history: MatchHistory) {
+        self.history = Some(history);
+    }
+
+    pub fn strategy_name(&self) -> &'static str {
+        self.strategy.lock().unwrap().name()
+    }
+
+    pub fn set_strategy(&mut self, strategy: Box<dyn Strategy>) {
+        *self.control.strategy_name.lock().unwrap() = strategy.name();
+        *self.strategy.lock().unwrap() = strategy;
+    }
+
+    /// A handle for changing the agent while it's running
+    pub fn control(&self) -> AgentControl {
+        self.control.clone()
+    }
+
+    pub fn difficulty(&self) -> &DifficultySettings {
+        &self.difficulty
+    }
+
+    /// Changes the difficulty. The cursor profile is updated on the next decision
+    pub fn set_difficulty(&mut self, difficulty: DifficultySettings) {
+        self.difficulty = difficulty;
+    }
+
+    /// Plays forever, or returns straight away if the bot is spectating
+    ///
+    /// Can get stuck as sometimes the wait_until conditions don't happen, but they all time out.
+    pub async fn run(&mut self, bot_handle: &BotHandle) {
+        if bot_handle.lock().await.is_spectating() {
+            info!("Spectating, not playing");
+            return;
+        }
+        bot_handle.lock().await.queue_action(Action::MakeReady);
+        bot_handle.wait_until(|bot| bot.state.initialized).await;
+        loop {
+            self.step(bot_handle).await;
+        }
+    }
+
+    /// Makes and carries out a single decision
+    pub async fn step(&mut self, bot_handle: &BotHandle) {
+        let next_strategy = self.control.next_strategy.lock().unwrap().take();
+        if let Some(strategy) = next_strategy {
+            info!("Switching to {} strategy", strategy.name());
+            self.set_strategy(strategy);
+        }
+        if self.control.is_paused() {
+            info!("Paused");
+            self.control.wait_until_resumed().await;
+            info!("Resumed");
+            return;
+        }
+
+        // Give humans a chance
+        let reaction = self.reaction_time();
+        if !reaction.is_zero() {
+            tokio::time::sleep(reaction).await;
+        }
+
+        let mut bot = bot_handle.lock().await;
+        bot.cursor.set_profile(self.difficulty.cursor);
+
+        // Not in the lobby yet
+        if bot.state.find_bot_player().is_none() {
+            drop(bot);
+            bot_handle
+                .wait_until(|bot| bot.state.find_bot_player().is_some())
+                .await;
+            return;
+        }
+
+        self.track_round(&bot.state);
+        // Searching can take a while, so it's done on a copy without holding up the bot
+        let state = Arc::new(bot.state.clone());
+        drop(bot);
+        let decision = match state.game_phase {
+            GamePhase::Play => self.decide(state.clone()).await,
+            _ if !state.bot_player().ready => Decision::Ready,
+            _ => Decision::Wait,
+        };
+        let decision = self.apply_difficulty(decision, state.bot_player().can_call_nerts);
+        // What the foundation looked like, to tell if someone plays there before the bot does
+        let expected_top = match decision {
+            Decision::Move {
+                to: Target::Foundation(i),
+                ..
+            }
+            | Decision::PlayHeld(Target::Foundation(i)) => state.foundation_top(i),
+            _ => None,
+        };
+        debug!("Decided {:?}", decision);
+        if decision != Decision::Wait {
+            bot_handle.emit(GameEvent::Decided(decision));
+        }
+        self.execute(bot_handle, decision, expected_top).await;
+    }
+
+    /// Asks the strategy what to do on a blocking thread
+    async fn decide(&self, state: Arc<GameState>) -> Decision {
+        let strategy = self.strategy.clone();
+        let difficulty = self.difficulty;
+        tokio::task::spawn_blocking(move || strategy.lock().unwrap().decide(&state, &difficulty))
+            .await
+            .expect("Strategy panicked")
+    }
+
+    /// Notices rounds starting and finishing, recording them once they finish
+    fn track_round(&mut self, state: &GameState) {
+        let rounds = state.scoreboard.rounds_played();
+        match self.round {
+            None if state.game_phase == GamePhase::Play => {
+                self.round = Some(RoundInProgress {
+                    started: Instant::now(),
+                    rounds_before: rounds,
+                    plays: 0,
+                });
+            }
+            Some(round) if rounds > round.rounds_before => {
+                self.round = None;
+                self.record_round(state, round);
+            }
+            // New match before the round finished
+            Some(round) if rounds < round.rounds_before => self.round = None,
+            _ => {}
+        }
+    }
+
+    fn record_round(&self, state: &GameState, round: RoundInProgress) {
+        let history = match &self.history {
+            Some(history) => history,
+            None => return,
+        };
+        let finished: Vec<_> = state
+            .scoreboard
+            .entries()
+            .iter()
+            .filter(|e| e.present && e.rounds.len() > round.rounds_before)
+            .collect();
+        let bot = state.bot_player();
+        let record = RoundRecord {
+            ended_at: SystemTime::now(),
+            duration: round.started.elapsed(),
+            strategy: self.strategy_name().to_string(),
+            bot_steam_id: bot.steam_id,
+            nerts_caller: finished
+                .iter()
+                .find(|e| e.rounds[round.rounds_before].nertsed)
+                .map(|e| e.steam_id),
+            plays: round.plays,
+            cards_left: bot.nerts_count as u32,
+            players: finished
+                .iter()
+                .map(|e| PlayerResult {
+                    steam_id: e.steam_id,
+                    points: e.rounds[round.rounds_before].points,
+                    total_score: e.total_score,
+                })
+                .collect(),
+        };
+        info!("Round finished, recording to {}", history.path().display());
+        if let Err(e) = history.append(&record) {
+            error!("Couldn't record round: {}", e);
+        }
+    }
+
+    fn reaction_time(&mut self) -> Duration {
+        let jitter = self.difficulty.reaction_jitter;
+        let jitter = if jitter.is_zero() {
+            Duration::ZERO
+        } else {
+            jitter.mul_f32(self.rng.gen())
+        };
+        self.difficulty.reaction_delay + jitter
+    }
+
+    /// Holds off calling nerts and makes mistakes
+    fn apply_difficulty(&mut self, decision: Decision, can_call_nerts: bool) -> Decision {
+        if !can_call_nerts {
+            self.nerts_possible_since = None;
+        }
+        match decision {
+            Decision::CallNerts => {
+                let since = *self.nerts_possible_since.get_or_insert_with(Instant::now);
+                if since.elapsed() < self.difficulty.nerts_call_delay {
+                    return Decision::Wait;
+                }
+                decision
+            }
+            Decision::Move { .. } if self.blunder() => {
+                debug!("Missed {:?}", decision);
+                Decision::Draw
+            }
+            Decision::PlayHeld(_) if self.blunder() => {
+                debug!("Fumbled {:?}", decision);
+                Decision::DropHeld
+            }
+            _ => decision,
+        }
+    }
+
+    fn blunder(&mut self) -> bool {
+        self.difficulty.mistake_chance > 0.0
+            && self.rng.gen::<f32>() < self.difficulty.mistake_chance
+    }
+
+    /// Somewhere random near the top left of a card, so clicks don't all land on the same pixel
+    ///
+    /// A `covered` card is under others in a table stack, so only a strip along its top edge is
+    /// showing. Cards on a `flipped` player's side are turned around, so their position is the
+    /// opposite corner.
+    fn click_offset(&mut self, covered: bool, flipped: bool) -> Position {
+        let x = self.rng.gen_range(10..50);
+        let y = if covered {
+            self.rng.gen_range(4..STACKED_CARDS_Y_OFFSET - 4)
+        } else {
+            self.rng.gen_range(10..80)
+        };
+        if flipped {
+            Position::new(-x, -y)
+        } else {
+            Position::new(x, y)
+        }
+    }
+
+    async fn execute(
+        &mut self,
+        bot_handle: &BotHandle,
+        decision: Decision,
+        expected_top: Option<CardData>,
+    ) {
+        let mut bot = bot_handle.lock().await;
+        match decision {
+            Decision::Ready => {
+                info!("Readying up");
+                bot.queue_action(Action::MakeReady);
+                drop(bot);
+                confirm(bot_handle, |bot| {
+                    bot.state.game_phase == GamePhase::Play || bot.state.bot_player().ready
+                })
+                .await;
+            }
+            Decision::CallNerts => {
+                info!("Calling nerts");
+                bot.queue_action(Action::MakeReady);
+                drop(bot);
+                confirm(bot_handle, |bot| !bot.state.bot_player().can_call_nerts).await;
+            }
+            Decision::Move { from, to } => {
+                let player = bot.state.bot_player();
+                let card = match from.card(player) {
+                    Some(card) => card,
+                    None => return,
+                };
+                let covered = matches!(from, Source::Stack(_, depth) if depth > 0);
+                match from {
+                    Source::Stack(_, depth) if depth > 0 => info!(
+                        "Picking up {} and {} on it to play on {:?}",
+                        card.as_small_string(),
+                        depth,
+                        to
+                    ),
+                    _ => info!("Picking up {} to play on {:?}", card.as_small_string(), to),
+                }
+                let pos = card.position + self.click_offset(covered, player.flipped);
+                bot.queue_action(Action::LeftClick(pos));
+                drop(bot);
+
+                // Wait until holding it then put it down
+                let holding = confirm(bot_handle, |bot| {
+                    !bot.state.bot_player().held_cards.cards.is_empty()
+                })
+                .await;
+                if holding {
+                    self.execute_play_held(bot_handle, to, expected_top).await;
+                }
+            }
+            Decision::PlayHeld(to) => {
+                drop(bot);
+                self.execute_play_held(bot_handle, to, expected_top).await;
+            }
+            Decision::DropHeld => {
+                info!(
+                    "Dropping {}",
+                    held_string(&bot.state.bot_player().held_cards)
+                );
+                bot.queue_action(Action::RightClick);
+                drop(bot);
+                confirm(bot_handle, |bot| {
+                    bot.state.bot_player().held_cards.cards.is_empty()
+                })
+                .await;
+            }
+            Decision::Draw => {
+                let before = bot.state.bot_player().draw_pile_up.as_ref().map(|c| c.data);
+                bot.queue_action(Action::Draw);
+                drop(bot);
+                confirm(bot_handle, |bot| {
+                    let player = bot.state.bot_player();
+                    player.draw_pile_up.as_ref().map(|c| c.data) != before || player.can_call_nerts
+                })
+                .await;
+            }
+            Decision::Wait => {
+                drop(bot);
+                bot_handle.wait_for_update().await;
+            }
+        }
+    }
+
+    /// Plays the held cards on `to`, which had `expected_top` on top if it's a foundation
+    ///
+    /// If the foundation changed since, the card can't go there any more so it's played on
+    /// another foundation if it can be. Anything that can't be put down where it's going is put
+    /// back.
+    async fn execute_play_held(
+        &mut self,
+        bot_handle: &BotHandle,
+        to: Target,
+        expected_top: Option<CardData>,
+    ) {
+        let mut bot = bot_handle.lock().await;
+        let held = match bot.state.bot_player().held_cards.bottom() {
+            Some(card) => card.clone(),
+            None => return,
+        };
+        let recovery = match to {
+            Target::Foundation(i) => race_recovery(&bot.state, &held, i, expected_top),
+            Target::Table(_) => None,
+        };
+        // Only plays someone else was going for count as races
+        let raced = match to {
+            Target::Foundation(i) => {
+                recovery.is_some() || bot.state.opponents.is_contested(i, CONTESTED_CONFIDENCE)
+            }
+            Target::Table(_) => false,
+        };
+        let to = match (to, recovery) {
+            (Target::Foundation(i), Some(recovery)) => {
+                info!(
+                    "Lost the race for {} on {:?}, {:?}",
+                    held.as_small_string(),
+                    to,
+                    recovery
+                );
+                if let Some(card) = held.data {
+                    bot_handle.emit(GameEvent::RaceLost {
+                        card,
+                        foundation: i,
+                        recovery,
+                    });
+                }
+                match recovery {
+                    RaceRecovery::Replanned(target) => Some(target),
+                    _ => None,
+                }
+            }
+            _ => Some(to),
+        };
+        // Could also have picked up more or fewer cards than planned
+        let to = match to.filter(|&to| held_fits(&bot.state, to)) {
+            Some(to) => to,
+            None => {
+                info!(
+                    "Putting {} back",
+                    held_string(&bot.state.bot_player().held_cards)
+                );
+                // Races are counted once per card, when it's played or put back
+                if recovery.is_some() {
+                    bot.metrics.race_finished(false);
+                }
+                bot.queue_action(Action::RightClick);
+                drop(bot);
+                confirm(bot_handle, |bot| {
+                    bot.state.bot_player().held_cards.cards.is_empty()
+                })
+                .await;
+                return;
+            }
+        };
+        // Only the bot's own table is turned around, not the center
+        let flipped = matches!(to, Target::Table(_)) && bot.state.bot_player().flipped;
+        let pos = match to.position(&bot.state) {
+            Some(pos) => pos + self.click_offset(false, flipped),
+            None => return,
+        };
+        info!(
+            "Playing {} on {:?}",
+            held_string(&bot.state.bot_player().held_cards),
+            to
+        );
+        let points_before = bot.state.bot_player().points_cards;
+        bot.queue_action(Action::LeftClick(pos));
+        drop(bot);
+        let placed = confirm(bot_handle, |bot| {
+            bot.state.bot_player().held_cards.cards.is_empty()
+        })
+        .await;
+        if !placed {
+            return;
+        }
+        // The card goes back where it came from if it can't go on the foundation any more
+        if let Target::Foundation(i) = to {
+            let mut bot = bot_handle.lock().await;
+            let won = !bot.state.play_rejected(points_before);
+            // Being put back means someone else got there first, even if nobody was seen coming
+            if raced || !won {
+                bot.metrics.race_finished(won);
+            }
+            if let (true, Some(round)) = (won, self.round.as_mut()) {
+                round.plays += 1;
+            }
+            if let (false, Some(card)) = (won, held.data) {
+                info!("Lost the race for {} on {:?}", held.as_small_string(), to);
+                bot_handle.emit(GameEvent::RaceLost {
+                    card,
+                    foundation: i,
+                    recovery: RaceRecovery::Rejected,
+                });
+            }
+        }
+    }
+}
+
+/// What to do with `card` if someone played on `foundation` since `expected_top` was on top: put
+/// it on another foundation if it fits one, otherwise put it back
+fn race_recovery(
+    state: &GameState,
+    card: &Card,
+    foundation: usize,
+    expected_top: Option<CardData>,
+) -> Option<RaceRecovery> {
+    if !state.foundation_changed(foundation, expected_top) {
+        return None;
+    }
+    Some(match state.foundation_for(card) {
+        Some(i) => RaceRecovery::Replanned(Target::Foundation(i)),
+        None => RaceRecovery::Returned,
+    })
+}
+
+/// The held card, or the bottom of a held stack and how many are on it
+fn held_string(held: &PlayedStack) -> String {
+    match (held.bottom(), held.cards.len()) {
+        (None, _) => "nothing".to_string(),
+        (Some(card), 1) => card.as_small_string(),
+        (Some(card), len) => format!("{} and {} on it", card.as_small_string(), len - 1),
+    }
+}
+
+/// Waits for the result of an action to show up in the state, counting it in
+/// [crate::Bot::metrics] if it does. Returns false if it timed out
+async fn confirm<F>(bot_handle: &BotHandle, test: F) -> bool
+where
+    F: Fn(&Bot) -> bool,
+{
+    bot_handle.wait_until(|bot| test(&bot)).await;
+    let mut bot = bot_handle.lock().await;
+    let confirmed = test(&bot);
+    if confirmed {
+        bot.metrics.action_confirmed(Instant::now());
+    }
+    confirmed
+}
+
+#[cfg(test)]
+mod tests {
+    use steamworks::SteamId;
+
+    use crate::{
+        state::{
+            card::{Suit, Value},
+            tests::{card, known_message, KNOWN_PLAYER},
+        },
+        strategy,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_race_recovery() {
+        let mut state = GameState::new(SteamId::from_raw(KNOWN_PLAYER));
+        state.update(&known_message());
+        let ace = CardData::new(Suit::Diamonds, Value::Ace);
+
+        // Nobody got there first
+        let two = card(Suit::Diamonds, Value::Two);
+        state.center_cards[0].1 = Some(card(Suit::Diamonds, Value::Ace));
+        assert_eq!(race_recovery(&state, &two, 0, Some(ace)), None);
+
+        // An ace can go on any empty foundation
+        let held_ace = card(Suit::Hearts, Value::Ace);
+        assert_eq!(
+            race_recovery(&state, &held_ace, 0, None),
+            Some(RaceRecovery::Replanned(Target::Foundation(1)))
+        );
+
+        // Someone else played the two of diamonds
+        state.center_cards[0].1 = Some(two.clone());
+        assert_eq!(
+            race_recovery(&state, &two, 0, Some(ace)),
+            Some(RaceRecovery::Returned)
+        );
+    }
+
+    #[test]
+    fn test_click_offset() {
+        let mut agent = Agent::new(
+            strategy::from_name("greedy").unwrap(),
+            DifficultySettings::default(),
+        );
+        for _ in 0..100 {
+            // Has to be in the strip showing above the next card down
+            let offset = agent.click_offset(true, false);
+            assert!((10..50).contains(&offset.x));
+            assert!((4..STACKED_CARDS_Y_OFFSET - 4).contains(&offset.y));
+
+            // Measured back from the other corner
+            let offset = agent.click_offset(true, true);
+            assert!((-49..=-10).contains(&offset.x));
+            assert!((-(STACKED_CARDS_Y_OFFSET - 5)..=-4).contains(&offset.y));
+
+            let offset = agent.click_offset(false, false);
+            assert!((10..80).contains(&offset.y));
+        }
+    }
+}
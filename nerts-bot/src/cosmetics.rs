@@ -0,0 +1,216 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use log::warn;
+use rand::Rng;
+use steamworks::SteamId;
+
+use crate::state::GameState;
+
+/// Pattern on the back of the bot's cards, by its index in the game
+///
+/// The game accepts 12 of them. What the game calls each one hasn't been checked, so they aren't
+/// named.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CardBack(u8);
+
+impl CardBack {
+    pub const COUNT: u8 = 12;
+
+    /// What the game calls it in messages
+    pub fn code(&self) -> u8 {
+        self.0
+    }
+
+    /// None if the game wouldn't accept it
+    pub fn from_code(code: u8) -> Option<Self> {
+        (code < Self::COUNT).then_some(Self(code))
+    }
+}
+
+/// Colour of the back of the bot's cards, also shown on face down cards to everyone
+///
+/// The game accepts 12 of them. Like [CardBack] they're only known by index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CardColor(u8);
+
+impl CardColor {
+    pub const COUNT: u8 = 12;
+
+    /// What the game calls it in messages
+    pub fn code(&self) -> u8 {
+        self.0
+    }
+
+    /// None if the game wouldn't accept it
+    pub fn from_code(code: u8) -> Option<Self> {
+        (code < Self::COUNT).then_some(Self(code))
+    }
+}
+
+/// How the bot's cards look to everyone
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Look {
+    pub back: CardBack,
+    pub color: CardColor,
+    /// Use the colour of whoever is on the same side of the table instead of `color`, if anyone
+    pub match_team: bool,
+}
+
+impl Look {
+    /// None if either code wouldn't be accepted by the game
+    pub fn from_codes(back: u8, color: u8) -> Option<Self> {
+        Some(Self {
+            back: CardBack::from_code(back)?,
+            color: CardColor::from_code(color)?,
+            match_team: false,
+        })
+    }
+
+    pub fn random(rng: &mut impl Rng) -> Self {
+        Self {
+            back: CardBack(rng.gen_range(0..CardBack::COUNT)),
+            color: CardColor(rng.gen_range(0..CardColor::COUNT)),
+            match_team: false,
+        }
+    }
+
+    /// The colour to use right now
+    ///
+    /// With `match_team` this is the colour of the first other player on the same side of the
+    /// table as the bot, falling back to `color` if there's nobody there.
+    pub fn color_in(&self, state: &GameState) -> CardColor {
+        if !self.match_team {
+            return self.color;
+        }
+        let bot = match state.find_bot_player() {
+            Some(bot) => bot,
+            None => return self.color,
+        };
+        state
+            .players
+            .iter()
+            .filter(|p| p.playing && p.steam_id != bot.steam_id && p.flipped == bot.flipped)
+            .find_map(|p| CardColor::from_code(p.card_color))
+            .unwrap_or(self.color)
+    }
+
+    /// One tab separated line, without the newline
+    fn to_line(self, steam_id: SteamId) -> String {
+        format!(
+            "{}\t{}\t{}\t{}",
+            steam_id.raw(),
+            self.back.code(),
+            self.color.code(),
+            self.match_team as u8
+        )
+    }
+
+    fn from_line(line: &str) -> Option<(SteamId, Self)> {
+        let fields: Vec<&str> = line.split('\t').collect();
+        let [steam_id, back, color, match_team] = fields.as_slice() else {
+            return None;
+        };
+        let look = Self {
+            back: CardBack::from_code(back.parse().ok()?)?,
+            color: CardColor::from_code(color.parse().ok()?)?,
+            match_team: *match_team == "1",
+        };
+        Some((SteamId::from_raw(steam_id.parse().ok()?), look))
+    }
+}
+
+/// The [Look] chosen for each bot profile, kept in a file with one line per steam account
+#[derive(Debug, Clone)]
+pub struct LookStore {
+    path: PathBuf,
+}
+
+impl LookStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The look saved for `steam_id`, None if nothing has been saved yet
+    pub fn load(&self, steam_id: SteamId) -> io::Result<Option<Look>> {
+        Ok(self
+            .looks()?
+            .into_iter()
+            .find(|(id, _)| *id == steam_id)
+            .map(|(_, look)| look))
+    }
+
+    /// Saves the look for `steam_id`, replacing whatever was saved before
+    pub fn save(&self, steam_id: SteamId, look: Look) -> io::Result<()> {
+        let mut looks = self.looks()?;
+        match looks.iter_mut().find(|(id, _)| *id == steam_id) {
+            Some((_, existing)) => *existing = look,
+            None => looks.push((steam_id, look)),
+        }
+        let mut file = fs::File::create(&self.path)?;
+        for (id, look) in looks {
+            writeln!(file, "{}", look.to_line(id))?;
+        }
+        Ok(())
+    }
+
+    fn looks(&self) -> io::Result<Vec<(SteamId, Look)>> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut looks = Vec::new();
+        for (i, line) in contents.lines().enumerate() {
+            if line.is_empty() {
+                continue;
+            }
+            match Look::from_line(line) {
+                Some(look) => looks.push(look),
+                None => warn!("Skipping bad line {} in {}", i + 1, self.path.display()),
+            }
+        }
+        Ok(looks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store() {
+        assert_eq!(Look::from_codes(11, 0).unwrap().back.code(), 11);
+        assert_eq!(Look::from_codes(12, 0), None);
+
+        let path = std::env::temp_dir().join(format!("nerts-looks-{}", std::process::id()));
+        let store = LookStore::new(&path);
+        let id = SteamId::from_raw(76561198064411451);
+        assert_eq!(store.load(id).unwrap(), None);
+
+        let look = Look {
+            match_team: true,
+            ..Look::from_codes(6, 5).unwrap()
+        };
+        store.save(id, Look::default()).unwrap();
+        store.save(SteamId::from_raw(1), Look::default()).unwrap();
+        store.save(id, look).unwrap();
+        assert_eq!(store.load(id).unwrap(), Some(look));
+        assert_eq!(
+            store.load(SteamId::from_raw(1)).unwrap(),
+            Some(Look::default())
+        );
+
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,292 @@
+use std::{collections::VecDeque, time::Duration};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use tokio::time::Instant;
+
+use crate::position::Position;
+
+/// Parameters controlling how the cursor moves between targets
+///
+/// Movement time follows Fitts' law,
+/// `base_time + time_per_bit * log2(distance / target_width + 1)`, and the path is a curve sent as
+/// several messages `step` apart.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MotionProfile {
+    /// Fitts' law intercept
+    pub base_time: Duration,
+    /// Fitts' law slope
+    pub time_per_bit: Duration,
+    /// Size of the thing being aimed at, roughly a card
+    pub target_width: f32,
+    /// Time between positions along the path
+    pub step: Duration,
+    /// How far the path bows away from a straight line, as a fraction of the distance
+    pub curvature: f32,
+    /// Maximum random offset added to points along the path
+    pub jitter: f32,
+    /// Chance of going past the target and coming back
+    pub overshoot_chance: f32,
+    /// How far past the target an overshoot goes, as a fraction of the distance
+    pub overshoot: f32,
+}
+
+impl MotionProfile {
+    /// Teleports straight to the target
+    pub const INSTANT: MotionProfile = MotionProfile {
+        base_time: Duration::ZERO,
+        time_per_bit: Duration::ZERO,
+        target_width: 120.0,
+        step: Duration::ZERO,
+        curvature: 0.0,
+        jitter: 0.0,
+        overshoot_chance: 0.0,
+        overshoot: 0.0,
+    };
+
+    /// Quick and fairly direct, like someone who knows what they're doing
+    pub const FAST: MotionProfile = MotionProfile {
+        base_time: Duration::from_millis(30),
+        time_per_bit: Duration::from_millis(40),
+        target_width: 120.0,
+        step: Duration::from_millis(16),
+        curvature: 0.1,
+        jitter: 2.0,
+        overshoot_chance: 0.1,
+        overshoot: 0.05,
+    };
+
+    /// Someone playing for fun
+    pub const CASUAL_HUMAN: MotionProfile = MotionProfile {
+        base_time: Duration::from_millis(100),
+        time_per_bit: Duration::from_millis(150),
+        target_width: 120.0,
+        step: Duration::from_millis(16),
+        curvature: 0.25,
+        jitter: 4.0,
+        overshoot_chance: 0.3,
+        overshoot: 0.08,
+    };
+
+    /// Blends between [MotionProfile::INSTANT] at `0.0` and [MotionProfile::CASUAL_HUMAN] at `1.0`
+    pub fn humanised(amount: f32) -> Self {
+        let t = amount.clamp(0.0, 1.0);
+        let (a, b) = (Self::INSTANT, Self::CASUAL_HUMAN);
+        let lerp = |x: f32, y: f32| x + (y - x) * t;
+        Self {
+            base_time: a.base_time.mul_f32(1.0 - t) + b.base_time.mul_f32(t),
+            time_per_bit: a.time_per_bit.mul_f32(1.0 - t) + b.time_per_bit.mul_f32(t),
+            target_width: lerp(a.target_width, b.target_width),
+            // Keep sending at the same rate, there's just fewer steps
+            step: if t > 0.0 { b.step } else { a.step },
+            curvature: lerp(a.curvature, b.curvature),
+            jitter: lerp(a.jitter, b.jitter),
+            overshoot_chance: lerp(a.overshoot_chance, b.overshoot_chance),
+            overshoot: lerp(a.overshoot, b.overshoot),
+        }
+    }
+
+    /// How long it should take to move `distance`
+    pub fn movement_time(&self, distance: f32) -> Duration {
+        if distance <= 0.0 {
+            return Duration::ZERO;
+        }
+        let bits = (distance / self.target_width + 1.0).log2();
+        self.base_time + self.time_per_bit.mul_f32(bits)
+    }
+}
+
+impl Default for MotionProfile {
+    fn default() -> Self {
+        Self::INSTANT
+    }
+}
+
+/// The bot's cursor, moving along a planned path one message at a time
+#[derive(Debug)]
+pub struct CursorMotion {
+    profile: MotionProfile,
+    position: Position,
+    /// Remaining points and when to send them
+    path: VecDeque<(Instant, Position)>,
+    rng: StdRng,
+}
+
+impl CursorMotion {
+    pub fn new(profile: MotionProfile) -> Self {
+        Self::with_rng(profile, StdRng::from_entropy())
+    }
+
+    pub fn with_rng(profile: MotionProfile, rng: StdRng) -> Self {
+        Self {
+            profile,
+            position: Position::zero(),
+            path: VecDeque::new(),
+            rng,
+        }
+    }
+
+    pub fn profile(&self) -> &MotionProfile {
+        &self.profile
+    }
+
+    /// Changes the profile. Takes effect from the next movement
+    pub fn set_profile(&mut self, profile: MotionProfile) {
+        self.profile = profile;
+    }
+
+    /// The position last sent to the server
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    pub fn is_moving(&self) -> bool {
+        !self.path.is_empty()
+    }
+
+    /// Where the cursor will end up
+    pub fn target(&self) -> Position {
+        self.path.back().map_or(self.position, |(_, p)| *p)
+    }
+
+    /// Plans a path to `target`, replacing any movement in progress
+    ///
+    /// With a profile that takes no time the cursor is moved immediately.
+    pub fn move_to(&mut self, target: Position, now: Instant) {
+        self.path.clear();
+        let distance = self.position.distance(target);
+        let duration = self.profile.movement_time(distance);
+        if self.profile.step.is_zero() || duration < self.profile.step {
+            self.position = target;
+            return;
+        }
+
+        let from = (self.position.x as f32, self.position.y as f32);
+        let to = (target.x as f32, target.y as f32);
+        let (dx, dy) = ((to.0 - from.0) / distance, (to.1 - from.1) / distance);
+
+        // Bow out to one side by a random amount
+        let bow = self.profile.curvature * distance * self.rng.gen_range(-1.0..=1.0);
+        let control = (
+            (from.0 + to.0) / 2.0 - dy * bow,
+            (from.1 + to.1) / 2.0 + dx * bow,
+        );
+
+        // Sometimes aim past the target, then correct with a few short steps
+        let overshoot = self.rng.gen::<f32>() < self.profile.overshoot_chance;
+        let end = if overshoot {
+            let extra = self.profile.overshoot * distance * self.rng.gen_range(0.5..=1.0);
+            (to.0 + dx * extra, to.1 + dy * extra)
+        } else {
+            to
+        };
+
+        let steps = (duration.as_secs_f32() / self.profile.step.as_secs_f32()).ceil() as u32;
+        let mut time = now;
+        for i in 1..=steps {
+            time += self.profile.step;
+            // Minimum jerk, starting and ending slowly
+            let t = i as f32 / steps as f32;
+            let s = t * t * t * (10.0 - 15.0 * t + 6.0 * t * t);
+            let x =
+                (1.0 - s) * (1.0 - s) * from.0 + 2.0 * (1.0 - s) * s * control.0 + s * s * end.0;
+            let y =
+                (1.0 - s) * (1.0 - s) * from.1 + 2.0 * (1.0 - s) * s * control.1 + s * s * end.1;
+            let point = if i == steps && !overshoot {
+                target
+            } else {
+                self.jittered(x, y)
+            };
+            self.path.push_back((time, point));
+        }
+        if overshoot {
+            for i in 1..=3 {
+                time += self.profile.step;
+                let t = i as f32 / 3.0;
+                let point = if i == 3 {
+                    target
+                } else {
+                    self.jittered(end.0 + (to.0 - end.0) * t, end.1 + (to.1 - end.1) * t)
+                };
+                self.path.push_back((time, point));
+            }
+        }
+    }
+
+    fn jittered(&mut self, x: f32, y: f32) -> Position {
+        let jitter = self.profile.jitter;
+        let (jx, jy) = if jitter > 0.0 {
+            (
+                self.rng.gen_range(-jitter..=jitter),
+                self.rng.gen_range(-jitter..=jitter),
+            )
+        } else {
+            (0.0, 0.0)
+        };
+        Position::new((x + jx).round() as i16, (y + jy).round() as i16)
+    }
+
+    /// Returns how long until the next point along the path is due, or `None` if not moving
+    pub fn time_until_next_step(&self, now: Instant) -> Option<Duration> {
+        self.path
+            .front()
+            .map(|(time, _)| time.saturating_duration_since(now))
+    }
+
+    /// Advances along the path, skipping any points that were missed
+    ///
+    /// Returns true if the cursor moved.
+    pub fn step(&mut self, now: Instant) -> bool {
+        let mut moved = false;
+        while let Some((time, point)) = self.path.front() {
+            if *time > now {
+                break;
+            }
+            self.position = *point;
+            self.path.pop_front();
+            moved = true;
+        }
+        moved
+    }
+
+    /// Stops moving, leaving the cursor wherever it currently is
+    pub fn stop(&mut self) {
+        self.path.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instant() {
+        let mut cursor = CursorMotion::new(MotionProfile::INSTANT);
+        cursor.move_to(Position::new(1000, 500), Instant::now());
+        assert!(!cursor.is_moving());
+        assert_eq!(cursor.position(), Position::new(1000, 500));
+    }
+
+    #[test]
+    fn test_path_ends_on_target() {
+        let start = Instant::now();
+        let target = Position::new(3000, 1500);
+        let mut cursor =
+            CursorMotion::with_rng(MotionProfile::CASUAL_HUMAN, StdRng::seed_from_u64(1));
+        cursor.move_to(target, start);
+        assert!(cursor.is_moving());
+        assert_eq!(cursor.target(), target);
+
+        // Takes several messages to get there
+        let mut messages = 0;
+        let mut now = start;
+        while cursor.is_moving() {
+            now += cursor.time_until_next_step(now).unwrap();
+            assert!(cursor.step(now));
+            messages += 1;
+        }
+        assert!(messages > 5);
+        assert_eq!(cursor.position(), target);
+        let expected = MotionProfile::CASUAL_HUMAN.movement_time(Position::zero().distance(target));
+        assert!(now - start >= expected);
+    }
+}
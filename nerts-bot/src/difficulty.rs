@@ -0,0 +1,100 @@
+use std::time::Duration;
+
+use crate::cursor::MotionProfile;
+
+/// [DifficultySettings::search_depth] of the hardest difficulty
+pub const MAX_SEARCH_DEPTH: u32 = 4;
+
+/// Preset skill levels for playing with people
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Difficulty {
+    Beginner,
+    Casual,
+    Skilled,
+    /// As fast as ping allows
+    #[default]
+    Unfair,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 4] = [
+        Difficulty::Beginner,
+        Difficulty::Casual,
+        Difficulty::Skilled,
+        Difficulty::Unfair,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Difficulty::Beginner => "beginner",
+            Difficulty::Casual => "casual",
+            Difficulty::Skilled => "skilled",
+            Difficulty::Unfair => "unfair",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|d| d.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn settings(&self) -> DifficultySettings {
+        match self {
+            Difficulty::Beginner => DifficultySettings {
+                reaction_delay: Duration::from_millis(1200),
+                reaction_jitter: Duration::from_millis(800),
+                search_depth: 1,
+                mistake_chance: 0.25,
+                nerts_call_delay: Duration::from_secs(4),
+                cursor: MotionProfile::CASUAL_HUMAN,
+            },
+            Difficulty::Casual => DifficultySettings {
+                reaction_delay: Duration::from_millis(600),
+                reaction_jitter: Duration::from_millis(400),
+                search_depth: 2,
+                mistake_chance: 0.1,
+                nerts_call_delay: Duration::from_secs(2),
+                cursor: MotionProfile::CASUAL_HUMAN,
+            },
+            Difficulty::Skilled => DifficultySettings {
+                reaction_delay: Duration::from_millis(250),
+                reaction_jitter: Duration::from_millis(150),
+                search_depth: 3,
+                mistake_chance: 0.02,
+                nerts_call_delay: Duration::from_millis(500),
+                cursor: MotionProfile::FAST,
+            },
+            Difficulty::Unfair => DifficultySettings {
+                reaction_delay: Duration::ZERO,
+                reaction_jitter: Duration::ZERO,
+                search_depth: MAX_SEARCH_DEPTH,
+                mistake_chance: 0.0,
+                nerts_call_delay: Duration::ZERO,
+                cursor: MotionProfile::INSTANT,
+            },
+        }
+    }
+}
+
+/// Everything a [Difficulty] controls
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DifficultySettings {
+    /// Minimum time between the bot seeing something and acting on it
+    pub reaction_delay: Duration,
+    /// Up to this much is randomly added to `reaction_delay`
+    pub reaction_jitter: Duration,
+    /// How many moves ahead strategies are allowed to look
+    pub search_depth: u32,
+    /// Chance of missing a play each decision
+    pub mistake_chance: f32,
+    /// How long to wait once nerts can be called before calling it
+    pub nerts_call_delay: Duration,
+    pub cursor: MotionProfile,
+}
+
+impl Default for DifficultySettings {
+    fn default() -> Self {
+        Difficulty::default().settings()
+    }
+}
@@ -8,6 +8,21 @@ pub enum BotError {
     #[error(transparent)]
     Steam(#[from] steamworks::SteamError),
 
+    #[error("Not connected to steam")]
+    NoSteam,
+
     #[error("Failed to join lobby")]
     JoinLobby,
+
+    #[error("Failed to send packet to server")]
+    SendPacket,
+
+    #[error("Task '{0}' panicked")]
+    TaskPanicked(&'static str),
+
+    #[error("Task '{task}' failed: {source}")]
+    TaskFailed {
+        task: &'static str,
+        source: Box<BotError>,
+    },
 }
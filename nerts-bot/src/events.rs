@@ -0,0 +1,45 @@
+use steamworks::SteamId;
+
+use crate::{
+    messages::{notification::NotificationType, server::GamePhase},
+    state::card::CardData,
+    strategy::{Decision, Target},
+};
+
+/// Something that happened in the game, sent to everyone subscribed with
+/// [crate::BotHandle::subscribe_events]
+#[derive(Debug, Clone, PartialEq)]
+pub enum GameEvent {
+    /// The game moved on to another phase
+    PhaseChanged { from: GamePhase, to: GamePhase },
+    /// A notification was shown
+    Notification {
+        kind: NotificationType,
+        /// Who it's about, if they're in the lobby
+        player: Option<SteamId>,
+    },
+    /// Nobody has played for a while so the draw piles will be shuffled in `seconds`
+    ShuffleCountdownStarted { seconds: u8 },
+    /// Every draw pile was shuffled, `count` times so far this round
+    Shuffled { count: u8 },
+    /// The bot's agent decided to do something other than wait
+    Decided(Decision),
+    /// Someone played on the foundation the bot was about to play `card` on
+    RaceLost {
+        card: CardData,
+        /// Index into [crate::state::GameState::center_cards]
+        foundation: usize,
+        recovery: RaceRecovery,
+    },
+}
+
+/// What the bot did with a card after losing the race to play it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RaceRecovery {
+    /// Played it somewhere else instead
+    Replanned(Target),
+    /// Put it back where it came from
+    Returned,
+    /// Only noticed once it was played, so the game put it back
+    Rejected,
+}
@@ -0,0 +1,296 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use log::warn;
+use steamworks::SteamId;
+
+/// How one player did in a recorded round
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlayerResult {
+    pub steam_id: SteamId,
+    /// Points from the round
+    pub points: i8,
+    /// Match score after the round
+    pub total_score: i16,
+}
+
+/// Everything recorded about a finished round
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoundRecord {
+    pub ended_at: SystemTime,
+    pub duration: Duration,
+    /// Name of the strategy the bot was playing with
+    pub strategy: String,
+    pub bot_steam_id: SteamId,
+    /// Whoever called nerts, if anyone did
+    pub nerts_caller: Option<SteamId>,
+    /// Cards the bot played on foundations
+    pub plays: u32,
+    /// Cards left in the bot's nerts pile
+    pub cards_left: u32,
+    pub players: Vec<PlayerResult>,
+}
+
+impl RoundRecord {
+    pub fn bot_result(&self) -> Option<&PlayerResult> {
+        self.players
+            .iter()
+            .find(|p| p.steam_id == self.bot_steam_id)
+    }
+
+    /// Returns true if the bot got the most points, including ties
+    pub fn is_win(&self) -> bool {
+        let best = self.players.iter().map(|p| p.points).max();
+        self.bot_result().is_some_and(|r| Some(r.points) == best)
+    }
+
+    /// One tab separated line, without the newline
+    fn to_line(&self) -> String {
+        let ended_at = self
+            .ended_at
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let nerts_caller = self
+            .nerts_caller
+            .map_or_else(|| "-".to_string(), |id| id.raw().to_string());
+        let players = self
+            .players
+            .iter()
+            .map(|p| format!("{}:{}:{}", p.steam_id.raw(), p.points, p.total_score))
+            .collect::<Vec<_>>()
+            .join(",");
+        format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            ended_at,
+            self.duration.as_millis(),
+            self.strategy,
+            self.bot_steam_id.raw(),
+            nerts_caller,
+            self.plays,
+            self.cards_left,
+            players
+        )
+    }
+
+    fn from_line(line: &str) -> Option<Self> {
+        let fields: Vec<&str> = line.split('\t').collect();
+        let [ended_at, duration, strategy, bot, nerts_caller, plays, cards_left, players] =
+            fields.as_slice()
+        else {
+            return None;
+        };
+        let players = if players.is_empty() {
+            Vec::new()
+        } else {
+            players
+                .split(',')
+                .map(|p| {
+                    let mut parts = p.split(':');
+                    let result = PlayerResult {
+                        steam_id: SteamId::from_raw(parts.next()?.parse().ok()?),
+                        points: parts.next()?.parse().ok()?,
+                        total_score: parts.next()?.parse().ok()?,
+                    };
+                    Some(result)
+                })
+                .collect::<Option<Vec<_>>>()?
+        };
+        Some(Self {
+            ended_at: UNIX_EPOCH + Duration::from_secs(ended_at.parse().ok()?),
+            duration: Duration::from_millis(duration.parse().ok()?),
+            strategy: strategy.to_string(),
+            bot_steam_id: SteamId::from_raw(bot.parse().ok()?),
+            nerts_caller: match *nerts_caller {
+                "-" => None,
+                id => Some(SteamId::from_raw(id.parse().ok()?)),
+            },
+            plays: plays.parse().ok()?,
+            cards_left: cards_left.parse().ok()?,
+            players,
+        })
+    }
+}
+
+/// Which records to return from [MatchHistory::query]
+#[derive(Debug, Clone, Default)]
+pub struct HistoryQuery {
+    /// Only rounds that ended at or after this
+    pub since: Option<SystemTime>,
+    /// Only rounds played with this strategy
+    pub strategy: Option<String>,
+}
+
+impl HistoryQuery {
+    pub fn matches(&self, record: &RoundRecord) -> bool {
+        self.since.is_none_or(|since| record.ended_at >= since)
+            && self
+                .strategy
+                .as_ref()
+                .is_none_or(|strategy| *strategy == record.strategy)
+    }
+}
+
+/// Results of the bot over some rounds
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Summary {
+    pub rounds: u32,
+    pub wins: u32,
+    /// Rounds the bot called nerts
+    pub nerts_called: u32,
+    pub points: i64,
+    pub plays: u64,
+}
+
+impl Summary {
+    pub fn from_records<'a>(records: impl IntoIterator<Item = &'a RoundRecord>) -> Self {
+        let mut summary = Self::default();
+        for record in records {
+            summary.rounds += 1;
+            summary.wins += record.is_win() as u32;
+            summary.nerts_called += (record.nerts_caller == Some(record.bot_steam_id)) as u32;
+            summary.points += record.bot_result().map_or(0, |r| r.points as i64);
+            summary.plays += record.plays as u64;
+        }
+        summary
+    }
+
+    pub fn win_rate(&self) -> f32 {
+        if self.rounds == 0 {
+            return 0.0;
+        }
+        self.wins as f32 / self.rounds as f32
+    }
+
+    pub fn average_points(&self) -> f32 {
+        if self.rounds == 0 {
+            return 0.0;
+        }
+        self.points as f32 / self.rounds as f32
+    }
+}
+
+/// Every round the bot has finished, kept in a file with one line per round
+///
+/// Rounds are only ever appended, so the file can be copied or trimmed by hand at any time. Lines
+/// that can't be read are skipped.
+#[derive(Debug, Clone)]
+pub struct MatchHistory {
+    path: PathBuf,
+}
+
+impl MatchHistory {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn append(&self, record: &RoundRecord) -> io::Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", record.to_line())
+    }
+
+    /// Every recorded round, oldest first. Empty if nothing has been recorded yet
+    pub fn records(&self) -> io::Result<Vec<RoundRecord>> {
+        self.query(&HistoryQuery::default())
+    }
+
+    pub fn query(&self, query: &HistoryQuery) -> io::Result<Vec<RoundRecord>> {
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut records = Vec::new();
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+            match RoundRecord::from_line(&line) {
+                Some(record) if query.matches(&record) => records.push(record),
+                Some(_) => {}
+                None => warn!("Skipping bad line {} in {}", i + 1, self.path.display()),
+            }
+        }
+        Ok(records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(strategy: &str, ended_at: u64, points: [i8; 2]) -> RoundRecord {
+        RoundRecord {
+            ended_at: UNIX_EPOCH + Duration::from_secs(ended_at),
+            duration: Duration::from_millis(95_250),
+            strategy: strategy.to_string(),
+            bot_steam_id: SteamId::from_raw(1),
+            nerts_caller: (points[0] > points[1]).then(|| SteamId::from_raw(1)),
+            plays: 20,
+            cards_left: 0,
+            players: vec![
+                PlayerResult {
+                    steam_id: SteamId::from_raw(1),
+                    points: points[0],
+                    total_score: points[0] as i16,
+                },
+                PlayerResult {
+                    steam_id: SteamId::from_raw(76561198064411451),
+                    points: points[1],
+                    total_score: points[1] as i16,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_append_and_query() {
+        let path = std::env::temp_dir().join(format!("nerts-history-{}", std::process::id()));
+        let history = MatchHistory::new(&path);
+        assert!(history.records().unwrap().is_empty());
+
+        let rounds = [
+            record("greedy", 1000, [18, -4]),
+            record("greedy", 2000, [-6, 12]),
+            record("planner", 3000, [20, 3]),
+        ];
+        for round in rounds.iter() {
+            history.append(round).unwrap();
+        }
+        assert_eq!(history.records().unwrap(), rounds);
+
+        let greedy = history
+            .query(&HistoryQuery {
+                strategy: Some("greedy".to_string()),
+                ..HistoryQuery::default()
+            })
+            .unwrap();
+        let summary = Summary::from_records(&greedy);
+        assert_eq!(summary.rounds, 2);
+        assert_eq!(summary.win_rate(), 0.5);
+        assert_eq!(summary.nerts_called, 1);
+        assert_eq!(summary.average_points(), 6.0);
+
+        let recent = history
+            .query(&HistoryQuery {
+                since: Some(UNIX_EPOCH + Duration::from_secs(2000)),
+                ..HistoryQuery::default()
+            })
+            .unwrap();
+        assert_eq!(recent.len(), 2);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
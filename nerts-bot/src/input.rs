@@ -0,0 +1,139 @@
+use std::{collections::VecDeque, time::Duration};
+
+use tokio::time::Instant;
+
+use crate::position::Position;
+
+/// A discrete input to send to the server
+///
+/// Each action is sent in its own ClientMessage, in the order they were queued.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Move the cursor to a position without clicking
+    MoveCursor(Position),
+    /// Move the cursor to a position and left click there
+    LeftClick(Position),
+    /// Right click wherever the cursor currently is
+    RightClick,
+    /// Draw from the draw pile
+    Draw,
+    /// Ready up in the lobby, or call nerts while playing
+    MakeReady,
+}
+
+impl Action {
+    /// Where the cursor needs to be before this action is sent
+    pub fn position(&self) -> Option<Position> {
+        match self {
+            Action::MoveCursor(pos) | Action::LeftClick(pos) => Some(*pos),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SendConfig {
+    /// How often a message is sent when nothing else is happening
+    pub heartbeat: Duration,
+    /// Minimum time between two messages carrying an action
+    pub min_action_spacing: Duration,
+}
+
+impl Default for SendConfig {
+    fn default() -> Self {
+        Self {
+            heartbeat: Duration::from_millis(100),
+            min_action_spacing: Duration::from_millis(20),
+        }
+    }
+}
+
+/// Actions waiting to be sent by the send loop
+#[derive(Debug, Default)]
+pub struct InputQueue {
+    actions: VecDeque<Action>,
+    last_action_sent: Option<Instant>,
+}
+
+impl InputQueue {
+    pub fn push(&mut self, action: Action) {
+        self.actions.push_back(action);
+    }
+
+    pub fn len(&self) -> usize {
+        self.actions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+
+    /// Removes all queued actions without sending them
+    pub fn clear(&mut self) {
+        self.actions.clear();
+    }
+
+    /// The next action to be sent
+    pub fn front(&self) -> Option<&Action> {
+        self.actions.front()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Action> {
+        self.actions.iter()
+    }
+
+    /// Returns how long until the next action can be sent, or `None` if nothing is queued
+    pub fn time_until_due(&self, now: Instant, spacing: Duration) -> Option<Duration> {
+        if self.actions.is_empty() {
+            return None;
+        }
+        Some(match self.last_action_sent {
+            Some(last) => (last + spacing).saturating_duration_since(now),
+            None => Duration::ZERO,
+        })
+    }
+
+    /// Takes the next action if enough time has passed since the last one was sent
+    pub fn pop_due(&mut self, now: Instant, spacing: Duration) -> Option<Action> {
+        if self.time_until_due(now, spacing)? > Duration::ZERO {
+            return None;
+        }
+        let action = self.actions.pop_front();
+        self.last_action_sent = Some(now);
+        action
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spacing() {
+        let spacing = Duration::from_millis(20);
+        let start = Instant::now();
+        let mut queue = InputQueue::default();
+        queue.push(Action::LeftClick(Position::new(1, 2)));
+        queue.push(Action::LeftClick(Position::new(3, 4)));
+
+        // Both clicks come out, in order, but not within the spacing
+        assert_eq!(
+            queue.pop_due(start, spacing),
+            Some(Action::LeftClick(Position::new(1, 2)))
+        );
+        assert_eq!(
+            queue.pop_due(start + Duration::from_millis(5), spacing),
+            None
+        );
+        assert_eq!(
+            queue.time_until_due(start + Duration::from_millis(5), spacing),
+            Some(Duration::from_millis(15))
+        );
+        assert_eq!(
+            queue.pop_due(start + spacing, spacing),
+            Some(Action::LeftClick(Position::new(3, 4)))
+        );
+        assert!(queue.is_empty());
+        assert_eq!(queue.time_until_due(start + spacing, spacing), None);
+    }
+}
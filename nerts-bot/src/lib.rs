@@ -1,34 +1,61 @@
-use std::{sync::Arc, time::Duration};
+use std::{path::PathBuf, sync::Arc, time::Duration};
 
 use compression::decompress;
-use error::BotError;
+use cursor::{CursorMotion, MotionProfile};
+use events::GameEvent;
+use input::{Action, InputQueue, SendConfig};
 use lobbyinfo::LobbyInfo;
-use log::{debug, trace};
+use log::{debug, error, info, trace};
 use messages::{
     client::ClientMessage,
     io::{reader::MessageReader, writer::MessageWriter},
     server::ServerMessage,
 };
+use metrics::Metrics;
+use recording::PacketRecorder;
 use state::GameState;
-use steamworks::{CallbackHandle, FriendFlags, P2PSessionRequest, SteamId};
-use tokio::sync::{broadcast, mpsc, oneshot, Mutex, MutexGuard};
+use steamworks::{FriendFlags, SteamId};
+use supervisor::{RestartPolicy, ShutdownSignal, ShutdownTrigger, Supervisor};
+use tokio::{
+    sync::{broadcast, oneshot, Mutex, MutexGuard, Notify},
+    time::Instant,
+};
+use transport::{SteamTransport, Transport};
 
+pub mod agent;
 pub mod compression;
+pub mod cosmetics;
+pub mod cursor;
+pub mod difficulty;
 mod error;
+pub mod events;
+pub mod history;
+pub mod input;
 pub mod lobbyinfo;
 pub mod messages;
+pub mod metrics;
+pub mod pool;
 pub mod position;
+pub mod recording;
+#[cfg(feature = "serde")]
+mod serde_steam_id;
 pub mod state;
+pub mod strategy;
+pub mod supervisor;
+pub mod transport;
+
+pub use error::BotError;
 
 type Result<T> = std::result::Result<T, BotError>;
 
 const APP_ID: u32 = 1131190;
-const TO_CLIENT_CHANNEL: i32 = 1;
-const TO_SERVER_CHANNEL: i32 = 2;
 
 #[derive(Clone)]
 pub struct BotHandle {
     bot: Arc<Mutex<Bot>>,
+    supervisor: Arc<Mutex<Supervisor>>,
+    shutdown_trigger: ShutdownTrigger,
+    events_tx: broadcast::Sender<GameEvent>,
 }
 
 impl BotHandle {
@@ -53,162 +80,246 @@ impl BotHandle {
             _ = tokio::time::sleep(Duration::from_millis(5000)) => {},
         }
     }
+
+    /// Waits until the next ServerMessage has been handled, or 5 seconds
+    pub async fn wait_for_update(&self) {
+        let mut data_received_rx = self.lock().await.data_received_tx.subscribe();
+        let _ = tokio::time::timeout(Duration::from_millis(5000), data_received_rx.recv()).await;
+    }
+
+    /// Receives every [GameEvent] from now on
+    pub fn subscribe_events(&self) -> broadcast::Receiver<GameEvent> {
+        self.events_tx.subscribe()
+    }
+
+    /// Sends an event to everyone subscribed, for things that happen outside of [GameState]
+    pub fn emit(&self, event: GameEvent) {
+        let _ = self.events_tx.send(event);
+    }
+
+    /// Waits until all of the bot's tasks have stopped
+    ///
+    /// Only returns early if a task fails more times than its [RestartPolicy] allows, in which case
+    /// the error is returned and the remaining tasks are shut down. Can be cancelled, like by
+    /// racing it against ctrl-c, and [BotHandle::shutdown] still waits for every task.
+    pub async fn join(&self) -> Result<()> {
+        self.supervisor.lock().await.join().await
+    }
+
+    /// Stops the bot
+    ///
+    /// The send loop sends one last message before stopping so anything requested beforehand still
+    /// reaches the server. Returns the first error any task failed with.
+    pub async fn shutdown(&self) -> Result<()> {
+        self.shutdown_trigger.trigger();
+        self.join().await
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BotConfig {
+    /// Applied to the transport callback, send and receive tasks
+    pub restart_policy: RestartPolicy,
+    pub send: SendConfig,
+    pub cursor: MotionProfile,
+    /// Join games without ever readying up, clicking or drawing, only following along
+    ///
+    /// Any action queued is dropped. Events are logged at info level so the game can be
+    /// reconstructed from the log afterwards.
+    pub spectate: bool,
+    /// Record every packet from the server to this file, see [recording]
+    pub record: Option<PathBuf>,
 }
 
 pub struct Bot {
-    pub client: steamworks::Client<steamworks::ClientManager>,
-    /// Will shut down the callback thread when dropped
-    _shutdown_tx: broadcast::Sender<()>,
+    transport: Arc<dyn Transport>,
     lobby: Option<LobbyInfo>,
     server_id: Option<SteamId>,
     last_data: Option<Vec<u8>>,
-    send_client_message_tx: mpsc::Sender<()>,
+    send_notify: Arc<Notify>,
+    pub input: InputQueue,
+    pub cursor: CursorMotion,
     pub state: GameState,
-    _session_req_cb: CallbackHandle,
+    pub metrics: Metrics,
+    spectating: bool,
+    recorder: Option<PacketRecorder>,
     data_received_tx: broadcast::Sender<()>,
+    events_tx: broadcast::Sender<GameEvent>,
 }
 
 impl Bot {
-    pub async fn start() -> Result<BotHandle> {
-        // Login with steam
-        let (client, single_client) = steamworks::Client::init_app(APP_ID)?;
-        let client_ = client.clone();
-        let steam_id = client.user().steam_id();
-        debug!("Connected to steam as {}", steam_id.raw());
-
-        // Accept all p2p requests
-        let _session_req_cb = client.register_callback(move |req: P2PSessionRequest| {
-            debug!("Accepted p2p {:?}", req.remote);
-            client_.networking().accept_p2p_session(req.remote);
-        });
+    /// Starts a bot playing through steam as whoever is logged in
+    pub async fn start(config: BotConfig) -> Result<BotHandle> {
+        Self::start_with(SteamTransport::init()?, config).await
+    }
+
+    /// Starts a bot sending and receiving packets through `transport`
+    pub async fn start_with(
+        transport: impl Transport + 'static,
+        config: BotConfig,
+    ) -> Result<BotHandle> {
+        let transport: Arc<dyn Transport> = Arc::new(transport);
+        let steam_id = transport.steam_id();
 
         let (data_received_tx, _) = broadcast::channel(10);
+        let (events_tx, _) = broadcast::channel(64);
 
-        let (send_client_message_tx, send_client_message_rx) = mpsc::channel::<()>(10);
-        let (shutdown_tx, mut shutdown_rx1) = broadcast::channel::<()>(1);
-        let mut shutdown_rx2 = shutdown_tx.subscribe();
-        let mut shutdown_rx3 = shutdown_tx.subscribe();
-        let client_1 = client.clone();
-        let client_2 = client.clone();
+        let recorder = match &config.record {
+            Some(path) => Some(PacketRecorder::create(path, steam_id)?),
+            None => None,
+        };
+
+        let send_notify = Arc::new(Notify::new());
         let bot = Bot {
-            client,
-            _shutdown_tx: shutdown_tx,
+            transport: transport.clone(),
             lobby: None,
             server_id: None,
             last_data: None,
-            send_client_message_tx,
+            send_notify: send_notify.clone(),
+            input: InputQueue::default(),
+            cursor: CursorMotion::new(config.cursor),
             state: GameState::new(steam_id),
-            _session_req_cb,
+            metrics: Metrics::default(),
+            spectating: config.spectate,
+            recorder,
             data_received_tx,
+            events_tx: events_tx.clone(),
         };
+        let supervisor = Supervisor::new();
         let handle = BotHandle {
             bot: Arc::new(Mutex::new(bot)),
+            shutdown_trigger: supervisor.shutdown_trigger(),
+            supervisor: Arc::new(Mutex::new(supervisor)),
+            events_tx,
         };
-
-        // Steam callback task
-        let _ = tokio::task::spawn_blocking(move || loop {
-            if matches!(
-                shutdown_rx1.try_recv(),
-                Err(broadcast::error::TryRecvError::Closed)
-            ) {
-                break;
+        let mut supervisor = handle.supervisor.lock().await;
+        let policy = config.restart_policy;
+
+        // Transport callback task
+        let transport_ = transport.clone();
+        supervisor.spawn("callbacks", policy, move |shutdown| {
+            let transport = transport_.clone();
+            async move {
+                while !shutdown.is_triggered() {
+                    transport.run_callbacks();
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+                Ok(())
             }
-            single_client.run_callbacks();
-            std::thread::sleep(Duration::from_millis(10));
         });
 
         // Start send loop
         let handle_ = handle.clone();
-        let _ = tokio::spawn(async move {
-            tokio::select! {
-                _ = shutdown_rx2.recv() => {}
-                _ = Bot::send_loop(handle_, client_1, send_client_message_rx) => {}
-            }
+        let transport_ = transport.clone();
+        supervisor.spawn("send", policy, move |shutdown| {
+            Bot::send_loop(
+                handle_.clone(),
+                transport_.clone(),
+                send_notify.clone(),
+                config.send,
+                shutdown,
+            )
         });
 
         // Start receive loop
         let handle_ = handle.clone();
-        let _ = tokio::spawn(async move {
-            tokio::select! {
-                _ = shutdown_rx3.recv() => {}
-                _ = Bot::receive_loop(handle_, client_2) => {}
-            };
+        supervisor.spawn("receive", policy, move |shutdown| {
+            Bot::receive_loop(handle_.clone(), transport.clone(), shutdown)
         });
 
+        drop(supervisor);
         Ok(handle)
     }
 
-    /// Sends current information frequently or updated information on events like clicking, nerts, etc.
+    /// Sends current information every heartbeat, or sooner when requested or when a queued action
+    /// is due
     async fn send_loop(
         bot: BotHandle,
-        client: steamworks::Client<steamworks::ClientManager>,
-        mut send_client_message_rx: mpsc::Receiver<()>,
+        transport: Arc<dyn Transport>,
+        send_notify: Arc<Notify>,
+        config: SendConfig,
+        mut shutdown: ShutdownSignal,
     ) -> Result<()> {
         loop {
-            // Wait for event or timeout
-            tokio::select! {
-                _ = send_client_message_rx.recv() => {}
-                _ = tokio::time::sleep(Duration::from_millis(100)) => {}
+            // Wait for the next cursor step or action, a request or the heartbeat
+            // On shutdown carry on to send whatever is left then stop
+            let wait = bot
+                .lock()
+                .await
+                .time_until_next_message(Instant::now(), &config);
+            if shutdown.is_triggered() {
+                tokio::time::sleep(wait).await;
+            } else {
+                tokio::select! {
+                    _ = send_notify.notified() => {}
+                    _ = tokio::time::sleep(wait) => {}
+                    _ = shutdown.triggered() => {}
+                }
             }
 
             // Get message and server id from bot
-            let (message, server_id) = {
+            // Actions stay queued until there's a server to send them to
+            let (message, server_id, drained) = {
                 let mut bot = bot.lock().await;
-                (bot.create_client_message(), bot.server_id)
+                let server_id = bot.server_id;
+                let message = server_id
+                    .map(|_| bot.create_client_message(Instant::now(), config.min_action_spacing));
+                let drained = bot.input.is_empty() && !bot.cursor.is_moving();
+                (message, server_id, drained || server_id.is_none())
             };
 
             // If not connected to server, skip
-            if server_id.is_none() {
-                continue;
+            if let (Some(message), Some(server_id)) = (message, server_id) {
+                // Serialize and send
+                trace!("Sending {:?}", message);
+                let mut w = MessageWriter::new();
+                w.write(message);
+                transport.send(server_id, &w.finish())?;
+            }
+
+            if shutdown.is_triggered() && drained {
+                return Ok(());
             }
-            let server_id = server_id.unwrap();
-
-            // Serialize and send
-            trace!("Sending {:?}", message);
-            let mut w = MessageWriter::new();
-            w.write(message);
-            let res = client.networking().send_p2p_packet(
-                server_id,
-                steamworks::SendType::Reliable,
-                &w.finish(),
-                TO_SERVER_CHANNEL,
-            );
-            assert!(res);
         }
     }
 
     /// Receives ServerMessages
     async fn receive_loop(
         bot: BotHandle,
-        client: steamworks::Client<steamworks::ClientManager>,
+        transport: Arc<dyn Transport>,
+        shutdown: ShutdownSignal,
     ) -> Result<()> {
-        loop {
-            let res = client.networking().is_p2p_packet_available();
-            if res.is_none() {
-                tokio::time::sleep(Duration::from_millis(10)).await;
-            }
-            let mut buf = vec![0u8; 0x1000];
-            let res = client
-                .networking()
-                .read_p2p_packet(&mut buf, TO_CLIENT_CHANNEL);
-            if res.is_none() {
-                continue;
-            }
-            let (steam_id, size) = res.unwrap();
-            buf.truncate(size);
-            let bot_ = bot.clone();
-            tokio::spawn(async move {
-                bot_.lock().await.handle_packet(steam_id, buf);
-            });
+        while !shutdown.is_triggered() {
+            let (steam_id, buf) = match transport.try_recv() {
+                Some(packet) => packet,
+                None => {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    continue;
+                }
+            };
+            // Handled in place so packets are applied in order and a panic while parsing is
+            // caught by the supervisor
+            bot.lock().await.handle_packet(steam_id, buf);
         }
+        Ok(())
+    }
+
+    /// The steam client, if the bot is playing through steam
+    pub fn steam_client(&self) -> Option<&steamworks::Client<steamworks::ClientManager>> {
+        self.transport.steam_client()
+    }
+
+    fn require_steam(&self) -> Result<&steamworks::Client<steamworks::ClientManager>> {
+        self.steam_client().ok_or(BotError::NoSteam)
     }
 
     pub async fn lobbies(&self) -> Result<Vec<LobbyInfo>> {
+        let client = self.require_steam()?;
         let mut lobbies = Vec::new();
 
         // Get public lobbies
         let (result_tx, result_rx) = oneshot::channel();
-        self.client.matchmaking().request_lobby_list(|result| {
+        client.matchmaking().request_lobby_list(|result| {
             result_tx.send(result).unwrap();
         });
 
@@ -218,7 +329,7 @@ impl Bot {
         }
 
         // Get friend lobbies
-        for friend in self.client.friends().get_friends(FriendFlags::IMMEDIATE) {
+        for friend in client.friends().get_friends(FriendFlags::IMMEDIATE) {
             if let Some(friend_game) = friend.game_played() {
                 if friend_game.game.app_id().0 == APP_ID && friend_game.lobby.raw() != 0 {
                     lobbies.push(LobbyInfo::FriendLobby(friend.id(), friend_game.lobby));
@@ -230,11 +341,12 @@ impl Bot {
     }
 
     pub async fn join_lobby(&mut self, lobby_info: LobbyInfo) -> Result<()> {
+        let client = self.require_steam()?.clone();
         let lobby_id = lobby_info.lobby_id();
 
         // Make request with steamworks
         let (result_tx, result_rx) = oneshot::channel();
-        self.client.matchmaking().join_lobby(lobby_id, |result| {
+        client.matchmaking().join_lobby(lobby_id, |result| {
             result_tx.send(result).unwrap();
         });
 
@@ -243,41 +355,99 @@ impl Bot {
         assert_eq!(result, lobby_id);
 
         self.lobby = Some(lobby_info);
-        self.server_id = Some(
-            self.client
-                .matchmaking()
-                .lobby_game_server(lobby_info.lobby_id())
-                .unwrap()
-                .steam_id
-                .unwrap(),
-        );
+        let server_id = client
+            .matchmaking()
+            .lobby_game_server(lobby_info.lobby_id())
+            .unwrap()
+            .steam_id
+            .unwrap();
+        self.connect(server_id);
+
+        Ok(())
+    }
+
+    /// Starts playing in the game hosted by `server_id`
+    ///
+    /// Done by [Bot::join_lobby] through steam, but can be used directly with other transports.
+    pub fn connect(&mut self, server_id: SteamId) {
+        self.server_id = Some(server_id);
 
         // Ask for keyframe and send first message
         self.state.send_key_frame = true;
-        self.send_client_message_tx.send(()).await.unwrap();
+        self.send_client_message();
+    }
 
-        Ok(())
+    /// Stops playing in the current game, leaving its steam lobby if it has one
+    ///
+    /// Anything still queued is dropped and the state starts over, keeping only the look.
+    pub fn leave_lobby(&mut self) {
+        let lobby = self.lobby.take();
+        if let (Some(lobby), Some(client)) = (lobby, self.steam_client()) {
+            client.matchmaking().leave_lobby(lobby.lobby_id());
+        }
+        self.server_id = None;
+        self.last_data = None;
+        self.input = InputQueue::default();
+        let look = self.state.target_look;
+        self.state = GameState::new(self.steam_id());
+        self.state.target_look = look;
+    }
+
+    /// The steam lobby the bot is in, if it joined one
+    pub fn lobby(&self) -> Option<LobbyInfo> {
+        self.lobby
+    }
+
+    /// The server the bot is playing with, if connected to one
+    pub fn server_id(&self) -> Option<SteamId> {
+        self.server_id
     }
 
     pub fn steam_id(&self) -> SteamId {
-        self.client.user().steam_id()
+        self.transport.steam_id()
     }
 
     /// Tells the bot to send a ClientMessage immediately
     ///
     /// The send loop will still need a lock on the bot to create a new message before it can send it.
-    pub async fn send_client_message(&self) {
-        self.send_client_message_tx.send(()).await.unwrap();
+    pub fn send_client_message(&self) {
+        self.send_notify.notify_one();
+    }
+
+    /// Whether the bot was started with [BotConfig::spectate]
+    pub fn is_spectating(&self) -> bool {
+        self.spectating
+    }
+
+    /// Queues an action to be sent as soon as the send cadence allows. Does nothing while
+    /// spectating
+    pub fn queue_action(&mut self, action: Action) {
+        if self.spectating {
+            debug!("Spectating, not sending {:?}", action);
+            return;
+        }
+        if !matches!(action, Action::MoveCursor(_)) {
+            self.metrics.actions_attempted += 1;
+        }
+        self.input.push(action);
+        self.send_client_message();
     }
 
     fn handle_packet(&mut self, steam_id: SteamId, data: Vec<u8>) {
         if Some(steam_id) != self.server_id {
             return;
         }
+        if let Some(recorder) = self.recorder.as_mut() {
+            if let Err(e) = recorder.record(&data) {
+                error!("Stopped recording: {}", e);
+                self.recorder = None;
+            }
+        }
+        let compressed_len = data.len();
         let data = decompress(&data);
-        let new_data;
-        match data[0] {
-            0 => new_data = data[1..].to_vec(),
+        self.metrics.packet_received(compressed_len, data.len());
+        let new_data: Vec<u8> = match data[0] {
+            0 => data[1..].to_vec(),
             1 => {
                 if self.last_data.is_none() {
                     return;
@@ -286,40 +456,108 @@ impl Bot {
                 if last_data.len() != data.len() - 1 {
                     unreachable!()
                 }
-                new_data = data[1..]
+                data[1..]
                     .iter()
                     .zip(last_data.iter())
                     .map(|(a, b)| a.wrapping_add(*b))
-                    .collect();
+                    .collect()
             }
             _ => unreachable!(),
-        }
+        };
         self.last_data = Some(new_data.clone());
         let mut r = MessageReader::new(&new_data);
         let message = r.read::<ServerMessage>();
         assert!(r.remaining_len() == 0);
         trace!("Received {:?}", message);
         self.state.update(&message);
+        for event in self.state.take_events() {
+            if self.spectating {
+                info!("Event {:?}", event);
+            } else {
+                debug!("Event {:?}", event);
+            }
+            let _ = self.events_tx.send(event);
+        }
         let _ = self.data_received_tx.send(());
     }
 
-    fn create_client_message(&mut self) -> ClientMessage {
+    fn time_until_next_message(&self, now: Instant, config: &SendConfig) -> Duration {
+        // Actions wait for the cursor to finish moving
+        let next = match self.cursor.time_until_next_step(now) {
+            Some(step) => Some(step),
+            None => self.input.time_until_due(now, config.min_action_spacing),
+        };
+        next.map_or(config.heartbeat, |d| d.min(config.heartbeat))
+    }
+
+    /// Creates the next message, either moving the cursor one step along its path or taking at
+    /// most one action from the input queue
+    fn create_client_message(&mut self, now: Instant, action_spacing: Duration) -> ClientMessage {
+        // Start moving to wherever the next action happens
+        if !self.cursor.is_moving() {
+            if let Some(pos) = self.input.front().and_then(Action::position) {
+                if pos != self.cursor.position() {
+                    self.cursor.move_to(pos, now);
+                }
+            }
+        }
+        let action = if self.cursor.is_moving() {
+            self.cursor.step(now);
+            None
+        } else {
+            self.input.pop_due(now, action_spacing)
+        };
+        // In case anything was pushed to the input queue directly
+        let action = action.filter(|_| !self.spectating);
+        if action.is_some_and(|a| !matches!(a, Action::MoveCursor(_))) {
+            self.metrics.action_sent(now);
+        }
+        self.state.target_cursor_pos = self.cursor.position();
         let message = ClientMessage {
             x: self.state.target_cursor_pos.x,
             y: self.state.target_cursor_pos.y,
-            left_click: self.state.send_left_click,
-            right_click: self.state.send_right_click,
-            make_ready: self.state.send_make_ready,
-            draw: self.state.send_draw,
-            card_back: self.state.target_card_back,
-            card_color: self.state.target_card_color,
+            left_click: matches!(action, Some(Action::LeftClick(_))),
+            right_click: action == Some(Action::RightClick),
+            make_ready: action == Some(Action::MakeReady),
+            draw: action == Some(Action::Draw),
+            card_back: self.state.target_look.back.code(),
+            card_color: self.state.target_look.color_in(&self.state).code(),
             send_key_frame: self.state.send_key_frame,
         };
-        self.state.send_left_click = false;
-        self.state.send_right_click = false;
-        self.state.send_make_ready = false;
-        self.state.send_draw = false;
         self.state.send_key_frame = false;
         message
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use transport::{LocalNetwork, Transport};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_spectate() {
+        let network = LocalNetwork::default();
+        let server = network.endpoint(SteamId::from_raw(1));
+        let config = BotConfig {
+            spectate: true,
+            ..BotConfig::default()
+        };
+        let handle = Bot::start_with(network.endpoint(SteamId::from_raw(2)), config)
+            .await
+            .unwrap();
+        {
+            let mut bot = handle.lock().await;
+            bot.connect(SteamId::from_raw(1));
+            bot.queue_action(Action::MakeReady);
+            bot.queue_action(Action::LeftClick(position::Position::new(100, 100)));
+            assert!(bot.input.is_empty());
+
+            bot.input.push(Action::Draw);
+            let message = bot.create_client_message(Instant::now(), Duration::ZERO);
+            assert!(!message.draw);
+        }
+        handle.shutdown().await.unwrap();
+        assert!(server.try_recv().is_some());
+    }
+}
@@ -16,11 +16,15 @@ impl LobbyInfo {
         }
     }
 
-    pub fn member_count(&self, bot: &Bot) -> usize {
-        bot.client.matchmaking().lobby_member_count(self.lobby_id())
+    /// None if the bot isn't playing through steam
+    pub fn member_count(&self, bot: &Bot) -> Option<usize> {
+        let client = bot.steam_client()?;
+        Some(client.matchmaking().lobby_member_count(self.lobby_id()))
     }
 
     pub fn member_limit(&self, bot: &Bot) -> Option<usize> {
-        bot.client.matchmaking().lobby_member_limit(self.lobby_id())
+        bot.steam_client()?
+            .matchmaking()
+            .lobby_member_limit(self.lobby_id())
     }
 }
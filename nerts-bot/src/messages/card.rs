@@ -1,6 +1,10 @@
-use super::io::reader::{Deserialize, MessageReader};
+use super::io::{
+    reader::{Deserialize, MessageReader},
+    writer::{MessageWriter, Serialize},
+};
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CardMessage {
     pub x: i16,
     pub y: i16,
@@ -23,6 +27,17 @@ impl Deserialize for CardMessage {
     }
 }
 
+impl Serialize for CardMessage {
+    fn serialize(&self, w: &mut MessageWriter) {
+        w.write(self.x);
+        w.write(self.y);
+        w.write(self.data);
+        w.write(self.flags);
+        w.write(self.height);
+        w.write(self.holder);
+    }
+}
+
 // #[derive(Debug)]
 // pub struct CardFlags {
 //     pub flags: u8,
@@ -1,6 +1,10 @@
-use super::io::reader::{Deserialize, MessageReader};
+use super::io::{
+    reader::{Deserialize, MessageReader},
+    writer::{MessageWriter, Serialize},
+};
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CardOutlineMessage {
     pub x: i16,
     pub y: i16,
@@ -14,3 +18,10 @@ impl Deserialize for CardOutlineMessage {
         }
     }
 }
+
+impl Serialize for CardOutlineMessage {
+    fn serialize(&self, w: &mut MessageWriter) {
+        w.write(self.x);
+        w.write(self.y);
+    }
+}
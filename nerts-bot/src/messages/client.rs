@@ -1,6 +1,7 @@
 use super::io::writer::{MessageWriter, Serialize};
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ClientMessage {
     pub x: i16,
     pub y: i16,
@@ -1,16 +1,73 @@
-use super::io::reader::{Deserialize, MessageReader};
+use super::io::{
+    reader::{Deserialize, MessageReader},
+    writer::{MessageWriter, Serialize},
+};
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NotificationMessage {
     pub player_id: u64,
-    pub notification_type: u8,
+    pub notification_type: NotificationType,
 }
 
 impl Deserialize for NotificationMessage {
     fn deserialize(r: &mut MessageReader) -> Self {
         NotificationMessage {
             player_id: r.read(),
-            notification_type: r.read(),
+            notification_type: NotificationType::new(r.read()),
+        }
+    }
+}
+
+impl Serialize for NotificationMessage {
+    fn serialize(&self, w: &mut MessageWriter) {
+        w.write(self.player_id);
+        w.write(self.notification_type.code());
+    }
+}
+
+/// What a notification is about
+///
+/// Like the game phases these are named from what shows up in game. Anything not worked out yet
+/// is kept as [NotificationType::Unknown] with its code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NotificationType {
+    /// A player called nerts, ending the round
+    CalledNerts,
+    /// Nobody could play so every draw pile was shuffled
+    Shuffled,
+    Unknown(u8),
+}
+
+impl NotificationType {
+    pub fn new(code: u8) -> Self {
+        match code {
+            0 => NotificationType::CalledNerts,
+            1 => NotificationType::Shuffled,
+            code => NotificationType::Unknown(code),
+        }
+    }
+
+    pub fn code(&self) -> u8 {
+        match self {
+            NotificationType::CalledNerts => 0,
+            NotificationType::Shuffled => 1,
+            NotificationType::Unknown(code) => *code,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codes() {
+        assert_eq!(NotificationType::new(0), NotificationType::CalledNerts);
+        assert_eq!(NotificationType::new(7), NotificationType::Unknown(7));
+        for code in 0..=u8::MAX {
+            assert_eq!(NotificationType::new(code).code(), code);
         }
     }
 }
@@ -1,6 +1,10 @@
-use super::io::reader::{Deserialize, MessageReader};
+use super::io::{
+    reader::{Deserialize, MessageReader},
+    writer::{MessageWriter, Serialize},
+};
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PlayerMessage {
     pub player_id: u64,
     pub origin_x: i16,
@@ -52,3 +56,29 @@ impl Deserialize for PlayerMessage {
         }
     }
 }
+
+impl Serialize for PlayerMessage {
+    fn serialize(&self, w: &mut MessageWriter) {
+        w.write(self.player_id);
+        w.write(self.origin_x);
+        w.write(self.origin_y);
+        w.write(self.flipped);
+        w.write(self.is_playing);
+        w.write(self.is_ready);
+        w.write(self.can_call_nerts);
+        w.write(self.show_deck_button);
+        w.write(self.effects);
+        w.write(self.card_color);
+        w.write(self.tableau_count);
+        w.write(self.called_nerts);
+        w.write(self.nerts_cards);
+        w.write(self.holding_nerts_card);
+        w.write(self.points_cards);
+        w.write(self.total_score);
+        w.write(&self.history_points);
+        w.write(&self.history_nertsed);
+        w.write(self.ignore_disable_foundation);
+        w.write(self.cursor_x);
+        w.write(self.cursor_y);
+    }
+}
@@ -1,12 +1,16 @@
 use super::{
     card::CardMessage,
     cardoutline::CardOutlineMessage,
-    io::reader::{Deserialize, MessageReader},
+    io::{
+        reader::{Deserialize, MessageReader},
+        writer::{MessageWriter, Serialize},
+    },
     notification::NotificationMessage,
     player::PlayerMessage,
 };
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ServerMessage {
     pub game_phase: GamePhase,
     pub player_messages: Vec<PlayerMessage>,
@@ -31,7 +35,21 @@ impl Deserialize for ServerMessage {
     }
 }
 
+/// Only needed to act as the server in local games
+impl Serialize for ServerMessage {
+    fn serialize(&self, w: &mut MessageWriter) {
+        w.write(self.game_phase.code());
+        w.write(&self.player_messages);
+        w.write(&self.card_messages);
+        w.write(&self.card_outline_messages);
+        w.write(&self.notification_message);
+        w.write(self.emergency_shuffle_countdown);
+        w.write(self.shuffle_count);
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum GamePhase {
     Lobby,
     Intro,
@@ -49,4 +67,8 @@ impl GamePhase {
             _ => unreachable!("Unknown game phase: {}", phase),
         }
     }
+
+    pub fn code(&self) -> u8 {
+        *self as u8
+    }
 }
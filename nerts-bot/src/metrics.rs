@@ -0,0 +1,229 @@
+use std::{fmt::Write, time::Duration};
+
+use tokio::time::Instant;
+
+/// Counts of how the bot is doing, kept in [crate::Bot::metrics]
+///
+/// Everything counts up from when the bot started. Keep a copy and compare with
+/// [Metrics::summary_since] for rates over a period, or scrape [Metrics::prometheus].
+#[derive(Debug, Clone)]
+pub struct Metrics {
+    started: Instant,
+    /// ServerMessages received
+    pub frames_received: u64,
+    /// Size of every packet as sent
+    pub compressed_bytes: u64,
+    /// Size of every packet after decompressing
+    pub uncompressed_bytes: u64,
+    /// Clicks, draws and readies queued
+    pub actions_attempted: u64,
+    /// Actions whose result showed up in the state
+    pub actions_confirmed: u64,
+    /// Cards played on a foundation that stayed there
+    pub races_won: u64,
+    /// Cards that couldn't be played on a foundation, usually because someone got there first
+    pub races_lost: u64,
+    /// Confirmed actions timed from when they were sent
+    pub round_trips: u64,
+    /// Time from sending an action to seeing its result, summed over every round trip
+    pub round_trip_total: Duration,
+    pub round_trip_max: Duration,
+    /// When the last action was sent, until its result is seen
+    last_action_sent: Option<Instant>,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            frames_received: 0,
+            compressed_bytes: 0,
+            uncompressed_bytes: 0,
+            actions_attempted: 0,
+            actions_confirmed: 0,
+            races_won: 0,
+            races_lost: 0,
+            round_trips: 0,
+            round_trip_total: Duration::ZERO,
+            round_trip_max: Duration::ZERO,
+            last_action_sent: None,
+        }
+    }
+}
+
+impl Metrics {
+    pub fn packet_received(&mut self, compressed_len: usize, uncompressed_len: usize) {
+        self.frames_received += 1;
+        self.compressed_bytes += compressed_len as u64;
+        self.uncompressed_bytes += uncompressed_len as u64;
+    }
+
+    pub fn action_sent(&mut self, now: Instant) {
+        self.last_action_sent = Some(now);
+    }
+
+    /// Counts an action as confirmed, timing it from when the last action was sent
+    pub fn action_confirmed(&mut self, now: Instant) {
+        self.actions_confirmed += 1;
+        if let Some(sent) = self.last_action_sent.take() {
+            let round_trip = now.saturating_duration_since(sent);
+            self.round_trips += 1;
+            self.round_trip_total += round_trip;
+            self.round_trip_max = self.round_trip_max.max(round_trip);
+        }
+    }
+
+    pub fn race_finished(&mut self, won: bool) {
+        if won {
+            self.races_won += 1;
+        } else {
+            self.races_lost += 1;
+        }
+    }
+
+    /// Average time from sending an action to seeing its result
+    pub fn average_round_trip(&self) -> Option<Duration> {
+        (self.round_trips > 0).then(|| self.round_trip_total / self.round_trips as u32)
+    }
+
+    pub fn uptime(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// One line on what's happened since `earlier`, a copy taken `period` ago, for logging every
+    /// so often
+    pub fn summary_since(&self, earlier: &Metrics, period: Duration) -> String {
+        let seconds = period.as_secs_f64().max(0.001);
+        let frames = self.frames_received - earlier.frames_received;
+        let compressed = self.compressed_bytes - earlier.compressed_bytes;
+        let uncompressed = self.uncompressed_bytes - earlier.uncompressed_bytes;
+        let confirmed = self.actions_confirmed - earlier.actions_confirmed;
+        let round_trips = self.round_trips - earlier.round_trips;
+        let round_trip = self
+            .round_trip_total
+            .saturating_sub(earlier.round_trip_total);
+        let mut summary = format!(
+            "{:.1} fps, {:.1} kB/s ({:.1} kB/s uncompressed), {}/{} actions confirmed",
+            frames as f64 / seconds,
+            compressed as f64 / seconds / 1000.0,
+            uncompressed as f64 / seconds / 1000.0,
+            confirmed,
+            self.actions_attempted - earlier.actions_attempted,
+        );
+        if round_trips > 0 {
+            let average = round_trip / round_trips as u32;
+            let _ = write!(summary, ", {} ms round trip", average.as_millis());
+        }
+        let _ = write!(
+            summary,
+            ", {}/{} races won",
+            self.races_won - earlier.races_won,
+            self.races_won + self.races_lost - earlier.races_won - earlier.races_lost
+        );
+        summary
+    }
+
+    /// Everything in the Prometheus text format
+    pub fn prometheus(&self) -> String {
+        let mut text = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, value: f64| {
+            let _ = writeln!(text, "# HELP nerts_{} {}", name, help);
+            let _ = writeln!(text, "# TYPE nerts_{} {}", name, kind);
+            let _ = writeln!(text, "nerts_{} {}", name, value);
+        };
+        metric(
+            "frames_received_total",
+            "counter",
+            "Server messages received",
+            self.frames_received as f64,
+        );
+        metric(
+            "received_bytes_total",
+            "counter",
+            "Bytes received as sent by the server",
+            self.compressed_bytes as f64,
+        );
+        metric(
+            "received_uncompressed_bytes_total",
+            "counter",
+            "Bytes received after decompressing",
+            self.uncompressed_bytes as f64,
+        );
+        metric(
+            "actions_attempted_total",
+            "counter",
+            "Clicks, draws and readies queued",
+            self.actions_attempted as f64,
+        );
+        metric(
+            "actions_confirmed_total",
+            "counter",
+            "Actions whose result showed up in the game state",
+            self.actions_confirmed as f64,
+        );
+        metric(
+            "races_won_total",
+            "counter",
+            "Cards played on a foundation that stayed there",
+            self.races_won as f64,
+        );
+        metric(
+            "races_lost_total",
+            "counter",
+            "Cards that couldn't be played on a foundation",
+            self.races_lost as f64,
+        );
+        metric(
+            "round_trips_total",
+            "counter",
+            "Confirmed actions timed from when they were sent",
+            self.round_trips as f64,
+        );
+        metric(
+            "round_trip_seconds_total",
+            "counter",
+            "Time from sending an action to seeing its result, summed",
+            self.round_trip_total.as_secs_f64(),
+        );
+        metric(
+            "round_trip_max_seconds",
+            "gauge",
+            "Longest time from sending an action to seeing its result",
+            self.round_trip_max.as_secs_f64(),
+        );
+        metric(
+            "uptime_seconds",
+            "gauge",
+            "Time since the bot started",
+            self.uptime().as_secs_f64(),
+        );
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut metrics = Metrics::default();
+        let start = Instant::now();
+        metrics.actions_attempted += 2;
+        metrics.action_sent(start);
+        metrics.action_confirmed(start + Duration::from_millis(40));
+        metrics.action_sent(start);
+        metrics.action_confirmed(start + Duration::from_millis(60));
+        assert_eq!(
+            metrics.average_round_trip(),
+            Some(Duration::from_millis(50))
+        );
+        assert_eq!(metrics.round_trip_max, Duration::from_millis(60));
+
+        let summary = metrics.summary_since(&Metrics::default(), Duration::from_secs(1));
+        assert!(summary.contains("2/2 actions confirmed, 50 ms round trip"));
+        assert!(metrics
+            .prometheus()
+            .contains("\nnerts_actions_confirmed_total 2\n"));
+    }
+}
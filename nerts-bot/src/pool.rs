@@ -0,0 +1,198 @@
+use tokio::task::JoinHandle;
+
+use crate::{agent::Agent, transport::Transport, Bot, BotConfig, BotHandle, Result};
+
+/// Several independent bots running in the same runtime
+///
+/// Every bot has its own transport, state and cursor, and can be given its own [Agent] to play
+/// with. Useful for filling local or simulated games with bots. Only one bot can play through
+/// steam, as steam can only be initialized once per process.
+#[derive(Default)]
+pub struct BotPool {
+    members: Vec<PoolMember>,
+}
+
+struct PoolMember {
+    handle: BotHandle,
+    agent: Option<JoinHandle<()>>,
+}
+
+impl BotPool {
+    /// Starts a new bot and returns its index in the pool
+    pub async fn add(
+        &mut self,
+        transport: impl Transport + 'static,
+        config: BotConfig,
+    ) -> Result<usize> {
+        let handle = Bot::start_with(transport, config).await?;
+        self.members.push(PoolMember {
+            handle,
+            agent: None,
+        });
+        Ok(self.members.len() - 1)
+    }
+
+    /// Has the bot at `index` play with `agent` until the pool shuts down, replacing any agent it
+    /// already had
+    pub fn play(&mut self, index: usize, mut agent: Agent) {
+        let member = &mut self.members[index];
+        if let Some(task) = member.agent.take() {
+            task.abort();
+        }
+        let handle = member.handle.clone();
+        member.agent = Some(tokio::spawn(async move { agent.run(&handle).await }));
+    }
+
+    pub fn get(&self, index: usize) -> Option<&BotHandle> {
+        self.members.get(index).map(|m| &m.handle)
+    }
+
+    pub fn handles(&self) -> impl Iterator<Item = &BotHandle> {
+        self.members.iter().map(|m| &m.handle)
+    }
+
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    /// Stops every agent then every bot. Returns the first error any bot failed with
+    pub async fn shutdown(self) -> Result<()> {
+        let mut result = Ok(());
+        for member in self.members {
+            if let Some(task) = member.agent {
+                task.abort();
+            }
+            let shutdown = member.handle.shutdown().await;
+            if result.is_ok() {
+                result = shutdown;
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use steamworks::SteamId;
+
+    use crate::{
+        compression::compress,
+        messages::{cardoutline::CardOutlineMessage, io::writer::Serialize, player::PlayerMessage},
+        position::Position,
+        state::{
+            card::{CardData, Suit, Value},
+            tests::{known_message, player_message},
+        },
+        transport::{LocalNetwork, Transport},
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_local_bots() {
+        let network = LocalNetwork::default();
+        let server_id = SteamId::from_raw(1);
+        let server = network.endpoint(server_id);
+
+        let mut pool = BotPool::default();
+        for id in 2..5 {
+            let transport = network.endpoint(SteamId::from_raw(id));
+            let i = pool.add(transport, BotConfig::default()).await.unwrap();
+            pool.get(i).unwrap().lock().await.connect(server_id);
+        }
+        assert_eq!(pool.len(), 3);
+
+        // Every bot sends its first message straight away
+        let mut senders = Vec::new();
+        while senders.len() < 3 {
+            match server.try_recv() {
+                Some((from, _)) if !senders.contains(&from) => senders.push(from),
+                Some(_) => {}
+                None => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        }
+        senders.sort_by_key(|id| id.raw());
+        assert_eq!(senders, (2..5).map(SteamId::from_raw).collect::<Vec<_>>());
+
+        pool.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_local_game() {
+        let network = LocalNetwork::default();
+        let server_id = SteamId::from_raw(1);
+        let server = network.endpoint(server_id);
+
+        // The known round with a fourth player sat on the flipped side
+        let mut message = known_message();
+        message.player_messages.push(PlayerMessage {
+            origin_x: 2660,
+            origin_y: 1382,
+            flipped: true,
+            tableau_count: 5,
+            ..player_message(76561198000000004)
+        });
+        // Four more foundations for them
+        for i in 0..4 {
+            message.card_outline_messages.push(CardOutlineMessage {
+                x: 2887 + 160 * i,
+                y: 1102,
+            });
+        }
+        let seats: Vec<(SteamId, bool, Position)> = message
+            .player_messages
+            .iter()
+            .map(|p| {
+                let origin = Position::new(p.origin_x, p.origin_y);
+                (SteamId::from_raw(p.player_id), p.flipped, origin)
+            })
+            .collect();
+
+        let mut pool = BotPool::default();
+        for (steam_id, _, _) in &seats {
+            let i = pool
+                .add(network.endpoint(*steam_id), BotConfig::default())
+                .await
+                .unwrap();
+            pool.get(i).unwrap().lock().await.connect(server_id);
+        }
+
+        let mut data = vec![0];
+        data.extend(message.serialize_bytes());
+        let packet = compress(&data);
+        for (steam_id, _, _) in &seats {
+            server.send(*steam_id, &packet).unwrap();
+        }
+
+        for (handle, (steam_id, flipped, origin)) in pool.handles().zip(&seats) {
+            handle
+                .wait_until(|bot| bot.state.find_bot_player().is_some())
+                .await;
+            let bot = handle.lock().await;
+            let player = bot.state.bot_player();
+            assert_eq!(player.steam_id, *steam_id);
+            assert_eq!(player.flipped, *flipped);
+            assert_eq!(player.origin, *origin);
+            assert_eq!(player.table.len(), 5);
+        }
+
+        // The first bot sees its own cards where the message put them
+        let bot = pool.get(0).unwrap().lock().await;
+        let table = &bot.state.bot_player().table;
+        assert_eq!(table[0].cards[0].position, Position::new(1014, 642));
+        assert_eq!(
+            table[0].cards[0].data,
+            Some(CardData::new(Suit::Clubs, Value::Four))
+        );
+        assert!(table.iter().all(|s| s.cards.len() == 1));
+        drop(bot);
+
+        pool.shutdown().await.unwrap();
+    }
+}
@@ -1,6 +1,7 @@
 use std::ops::{Add, Sub};
 
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Position {
     pub x: i16,
     pub y: i16,
@@ -16,9 +17,9 @@ impl Position {
     }
 
     pub fn distance(&self, other: Position) -> f32 {
-        let dx = self.x - other.x;
-        let dy = self.y - other.y;
-        ((dx * dx + dy * dy) as f32).sqrt()
+        let dx = self.x as f32 - other.x as f32;
+        let dy = self.y as f32 - other.y as f32;
+        (dx * dx + dy * dy).sqrt()
     }
 
     pub fn within_box(&self, box_origin: Position, box_size: Position) -> bool {
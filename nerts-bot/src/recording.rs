@@ -0,0 +1,141 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+    time::Duration,
+};
+
+use log::warn;
+use steamworks::SteamId;
+use tokio::time::Instant;
+
+use crate::{
+    transport::{LocalNetwork, Transport},
+    Bot, BotConfig, BotHandle, Result,
+};
+
+/// Writes every packet the server sends to a file so the game can be replayed with [replay]
+///
+/// The file starts with the bot's steam id, then each packet is the milliseconds since recording
+/// started, its length and the packet itself, all little endian.
+pub struct PacketRecorder {
+    file: BufWriter<File>,
+    started: Instant,
+}
+
+impl PacketRecorder {
+    pub fn create(path: &Path, steam_id: SteamId) -> io::Result<Self> {
+        let mut file = BufWriter::new(File::create(path)?);
+        file.write_all(&steam_id.raw().to_le_bytes())?;
+        Ok(Self {
+            file,
+            started: Instant::now(),
+        })
+    }
+
+    pub fn record(&mut self, data: &[u8]) -> io::Result<()> {
+        let millis = self.started.elapsed().as_millis() as u64;
+        self.file.write_all(&millis.to_le_bytes())?;
+        self.file.write_all(&(data.len() as u32).to_le_bytes())?;
+        self.file.write_all(data)?;
+        self.file.flush()
+    }
+}
+
+/// A game recorded by [PacketRecorder]
+#[derive(Debug, Clone)]
+pub struct Recording {
+    /// Who the bot was playing as
+    pub steam_id: SteamId,
+    /// Every packet with when it arrived
+    pub packets: Vec<(Duration, Vec<u8>)>,
+}
+
+impl Recording {
+    /// Reads a recording. A packet cut off at the end, like when the bot was killed, is skipped
+    pub fn read(path: &Path) -> io::Result<Self> {
+        let mut file = BufReader::new(File::open(path)?);
+        let mut id = [0; 8];
+        file.read_exact(&mut id)?;
+        let mut packets = Vec::new();
+        loop {
+            let mut header = [0; 12];
+            match file.read_exact(&mut header) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e),
+            }
+            let millis = u64::from_le_bytes(header[..8].try_into().unwrap());
+            let len = u32::from_le_bytes(header[8..].try_into().unwrap());
+            let mut data = vec![0; len as usize];
+            match file.read_exact(&mut data) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                    warn!(
+                        "Recording {} ends part way through a packet",
+                        path.display()
+                    );
+                    break;
+                }
+                Err(e) => return Err(e),
+            }
+            packets.push((Duration::from_millis(millis), data));
+        }
+        Ok(Self {
+            steam_id: SteamId::from_raw(u64::from_le_bytes(id)),
+            packets,
+        })
+    }
+}
+
+/// Plays a recorded game back through a spectating bot, at the speed it was recorded
+///
+/// The bot sees the game as the recording bot did, so [crate::state::GameState::bot_player] is
+/// still the player that recorded it.
+pub async fn replay(recording: Recording, config: BotConfig) -> Result<BotHandle> {
+    let network = LocalNetwork::default();
+    let server_id = SteamId::from_raw(0);
+    let server = network.endpoint(server_id);
+    let config = BotConfig {
+        spectate: true,
+        record: None,
+        ..config
+    };
+    let handle = Bot::start_with(network.endpoint(recording.steam_id), config).await?;
+    handle.lock().await.connect(server_id);
+
+    let started = Instant::now();
+    tokio::spawn(async move {
+        for (at, data) in recording.packets {
+            tokio::time::sleep_until(started + at).await;
+            // Throw away whatever the bot sent
+            while server.try_recv().is_some() {}
+            if server.send(recording.steam_id, &data).is_err() {
+                return;
+            }
+        }
+    });
+    Ok(handle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_back() {
+        let path = std::env::temp_dir().join(format!("nerts-recording-{}", std::process::id()));
+        let steam_id = SteamId::from_raw(76561198064411451);
+        let mut recorder = PacketRecorder::create(&path, steam_id).unwrap();
+        recorder.record(&[1, 2, 3]).unwrap();
+        recorder.record(&[]).unwrap();
+        drop(recorder);
+
+        let recording = Recording::read(&path).unwrap();
+        assert_eq!(recording.steam_id, steam_id);
+        let packets: Vec<&[u8]> = recording.packets.iter().map(|(_, p)| &p[..]).collect();
+        assert_eq!(packets, [&[1, 2, 3][..], &[]]);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,53 @@
+//! Serializes [SteamId]s as their raw u64, for use with `#[serde(with = "...")]`
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use steamworks::SteamId;
+
+pub fn serialize<S: Serializer>(steam_id: &SteamId, serializer: S) -> Result<S::Ok, S::Error> {
+    steam_id.raw().serialize(serializer)
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<SteamId, D::Error> {
+    u64::deserialize(deserializer).map(SteamId::from_raw)
+}
+
+pub mod option {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        steam_id: &Option<SteamId>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        steam_id.map(|id| id.raw()).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<SteamId>, D::Error> {
+        Ok(Option::<u64>::deserialize(deserializer)?.map(SteamId::from_raw))
+    }
+}
+
+/// Maps keyed by steam id. Keys become strings in formats like JSON
+pub mod map {
+    use super::*;
+
+    pub fn serialize<S: Serializer, V: Serialize>(
+        map: &HashMap<SteamId, V>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(map.iter().map(|(id, v)| (id.raw(), v)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, V: Deserialize<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<SteamId, V>, D::Error> {
+        let map = HashMap::<u64, V>::deserialize(deserializer)?;
+        Ok(map
+            .into_iter()
+            .map(|(id, v)| (SteamId::from_raw(id), v))
+            .collect())
+    }
+}
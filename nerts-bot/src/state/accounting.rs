@@ -0,0 +1,173 @@
+use std::collections::{HashMap, HashSet};
+
+use steamworks::SteamId;
+
+use super::{
+    card::{Card, CardData, Suit, Value},
+    player::Player,
+    DECK_SIZE,
+};
+
+/// Every card in a deck
+pub fn full_deck() -> impl Iterator<Item = CardData> {
+    [Suit::Clubs, Suit::Diamonds, Suit::Hearts, Suit::Spades]
+        .into_iter()
+        .flat_map(|suit| (0..13).map(move |code| CardData::new(suit, Value::from_code(code))))
+}
+
+/// Keeps track of which cards have been seen from each player's deck this round
+///
+/// Every player plays with their own full deck, so any card of theirs that hasn't been seen yet
+/// must be in one of their hidden places: under the top of the nerts pile, in the face down draw
+/// pile, or under the top of the face up draw pile if it was never shown while drawing.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CardAccounting {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_steam_id::map"))]
+    decks: HashMap<SteamId, DeckAccount>,
+}
+
+/// What's known about one player's deck
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeckAccount {
+    seen: HashSet<CardData>,
+    hidden_nerts: usize,
+}
+
+/// A place a player's face down cards can be
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hidden {
+    /// Under the top card of the nerts pile
+    Nerts,
+    /// Anywhere in the draw pile that hasn't been seen
+    DrawPile,
+}
+
+impl CardAccounting {
+    /// Forgets everything, for a new round
+    pub fn reset(&mut self) {
+        self.decks.clear();
+    }
+
+    /// Records every face up card currently visible for each playing player
+    pub fn update(&mut self, players: &[Player]) {
+        self.decks
+            .retain(|id, _| players.iter().any(|p| p.steam_id == *id));
+        for player in players.iter().filter(|p| p.playing) {
+            let deck = self.decks.entry(player.steam_id).or_default();
+            let visible = player
+                .nerts_cards
+                .iter()
+                .chain(player.draw_pile_up.iter())
+                .chain(player.table.iter().flat_map(|s| s.cards.iter()))
+                .chain(player.held_cards.cards.iter());
+            deck.seen.extend(visible.filter_map(Card::visible_data));
+            deck.hidden_nerts = player.nerts_cards.iter().filter(|c| !c.face_up).count();
+        }
+    }
+
+    /// Marks a card as seen in a player's deck, for cards known some other way
+    pub fn mark_seen(&mut self, steam_id: SteamId, card: CardData) {
+        self.decks.entry(steam_id).or_default().seen.insert(card);
+    }
+
+    pub fn deck(&self, steam_id: SteamId) -> Option<&DeckAccount> {
+        self.decks.get(&steam_id)
+    }
+}
+
+impl DeckAccount {
+    pub fn is_seen(&self, card: CardData) -> bool {
+        self.seen.contains(&card)
+    }
+
+    /// Every card that hasn't been seen, so could be in any hidden place
+    pub fn candidates(&self) -> impl Iterator<Item = CardData> + '_ {
+        full_deck().filter(|c| !self.seen.contains(c))
+    }
+
+    /// Number of cards that haven't been seen
+    pub fn hidden_count(&self) -> usize {
+        DECK_SIZE - self.seen.len()
+    }
+
+    /// How many hidden cards are in a place
+    pub fn hidden_in(&self, place: Hidden) -> usize {
+        match place {
+            Hidden::Nerts => self.hidden_nerts.min(self.hidden_count()),
+            Hidden::DrawPile => self.hidden_count().saturating_sub(self.hidden_nerts),
+        }
+    }
+
+    /// Chance that any one face down card in a place is `card`
+    ///
+    /// Nothing is known about the order of unseen cards, so it's the same for every card that
+    /// hasn't been seen.
+    pub fn probability(&self, card: CardData) -> f32 {
+        if self.is_seen(card) || self.hidden_count() == 0 {
+            0.0
+        } else {
+            1.0 / self.hidden_count() as f32
+        }
+    }
+
+    /// Chance that `card` is somewhere in a place
+    pub fn probability_in(&self, card: CardData, place: Hidden) -> f32 {
+        self.probability(card) * self.hidden_in(place) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        messages::{card::CardMessage, player::PlayerMessage},
+        state::tests::player_message,
+    };
+
+    use super::*;
+
+    fn card(x: i16, data: u8, face_up: bool) -> Card {
+        Card::from_message(&CardMessage {
+            x,
+            y: 642,
+            data,
+            flags: face_up as u8,
+            height: 0,
+            holder: 255,
+        })
+    }
+
+    #[test]
+    fn test_candidates() {
+        let mut player = Player::from_message(&PlayerMessage {
+            card_color: 6,
+            nerts_cards: 3,
+            ..player_message(1)
+        });
+        // Face down cards send the card back colour, which isn't a real card
+        player.nerts_cards = vec![
+            card(664, 22, true),
+            card(651, 6, false),
+            card(638, 6, false),
+        ];
+        for (i, data) in [3, 51, 10, 30].into_iter().enumerate() {
+            player.table[i].add_card(card(1014 + 160 * i as i16, data, true));
+        }
+
+        let mut accounting = CardAccounting::default();
+        accounting.update(&[player]);
+        let deck = accounting.deck(SteamId::from_raw(1)).unwrap();
+        assert_eq!(deck.hidden_count(), 47);
+        assert_eq!(deck.hidden_in(Hidden::Nerts), 2);
+        assert_eq!(deck.hidden_in(Hidden::DrawPile), 45);
+
+        let seven_of_clubs = CardData::new(Suit::Clubs, Value::Seven);
+        assert!(!deck.is_seen(seven_of_clubs));
+        assert_eq!(deck.probability(seven_of_clubs), 1.0 / 47.0);
+        let ten_of_diamonds = CardData::new(Suit::Diamonds, Value::Ten);
+        assert!(deck.is_seen(ten_of_diamonds));
+        assert_eq!(deck.probability_in(ten_of_diamonds, Hidden::Nerts), 0.0);
+        assert_eq!(deck.candidates().count(), 47);
+    }
+}
@@ -1,6 +1,7 @@
 use crate::{messages::card::CardMessage, position::Position};
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Card {
     pub data: Option<CardData>,
     pub position: Position,
@@ -52,6 +53,11 @@ impl Card {
         self_data.suit == other_data.suit && self_data.value.as_u8() == other_data.value.as_u8() + 1
     }
 
+    /// The card's suit and value if it's face up. Face down cards carry the card back instead
+    pub fn visible_data(&self) -> Option<CardData> {
+        self.face_up.then_some(self.data).flatten()
+    }
+
     pub fn as_small_string(&self) -> String {
         if let Some(data) = self.data.as_ref() {
             format!("{}{}", data.value.as_small_str(), data.suit.as_small_str())
@@ -61,13 +67,44 @@ impl Card {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CardData {
     pub suit: Suit,
     pub value: Value,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+impl CardData {
+    pub fn new(suit: Suit, value: Value) -> Self {
+        Self { suit, value }
+    }
+
+    /// Returns true if this card can be played on a foundation with `top` on top, or on an empty
+    /// foundation if `None`
+    pub fn can_play_on_foundation(&self, top: Option<&CardData>) -> bool {
+        match top {
+            Some(top) => self.suit == top.suit && self.value.as_u8() == top.value.as_u8() + 1,
+            None => self.value == Value::Ace,
+        }
+    }
+
+    /// Returns true if this card can be played on a table stack with `top` on top, or on an empty
+    /// stack if `None`
+    ///
+    /// Table stacks go down in value, alternating colors.
+    pub fn can_play_on_table(&self, top: Option<&CardData>) -> bool {
+        match top {
+            Some(top) => {
+                self.suit.is_red() != top.suit.is_red()
+                    && self.value.as_u8() + 1 == top.value.as_u8()
+            }
+            None => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Suit {
     Clubs,
     Diamonds,
@@ -84,9 +121,14 @@ impl Suit {
             Suit::Spades => "S",
         }
     }
+
+    pub fn is_red(&self) -> bool {
+        matches!(self, Suit::Diamonds | Suit::Hearts)
+    }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Value {
     Ace,
     Two,
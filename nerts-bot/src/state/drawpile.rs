@@ -0,0 +1,366 @@
+use super::card::CardData;
+
+/// Remembers the bot's draw pile as it's cycled through
+///
+/// Drawing moves up to three cards from the face down pile onto the face up pile, leaving only the
+/// last one visible. Once the face down pile is empty, drawing turns the face up pile back over
+/// in the same order. By watching the top card the model learns every card that's shown while
+/// cycling, and after one full pass it knows which card will be on top after any number of draws.
+/// Cards under the top are seen when the top card is played, so the order fills in over time.
+///
+/// The length of the pile needs to be known to know how many cards the last draw of a pass took.
+/// At the start of a round it's everything not dealt, see [DrawPileModel::with_len]. Otherwise
+/// it's only worked out after the first pass, assuming the last draw took three cards.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DrawPileModel {
+    /// Every card in the order they're drawn, `None` for cards not seen yet
+    cards: Vec<Option<CardData>>,
+    /// How many of `cards` are currently face up
+    drawn: usize,
+    /// Set if the pile was created with a known length, or once it's been turned back over
+    len_known: bool,
+    last_top: Option<CardData>,
+    last_face_down: bool,
+    /// The card last taken off the top and where it was, in case it's put back
+    picked_up: Option<(usize, CardData)>,
+}
+
+impl DrawPileModel {
+    /// A draw pile of `len` unseen cards, all face down
+    pub fn with_len(len: usize) -> Self {
+        Self {
+            cards: vec![None; len],
+            len_known: true,
+            last_face_down: len > 0,
+            ..Self::default()
+        }
+    }
+
+    /// Forgets everything, for when the pile has been shuffled or the bot joined mid-round
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Updates the model from what's currently visible
+    ///
+    /// `face_down` is whether there are any face down cards left, `top` is the visible face up
+    /// card and `held` is the single card the bot is holding.
+    pub fn observe(&mut self, face_down: bool, top: Option<CardData>, held: Option<CardData>) {
+        if top == self.last_top && face_down == self.last_face_down {
+            return;
+        }
+        let last_top = self.last_top;
+        let last_face_down = self.last_face_down;
+        self.last_top = top;
+        self.last_face_down = face_down;
+
+        // Picked up card put back
+        if let Some((i, card)) = self.picked_up {
+            if top == Some(card) && held != Some(card) {
+                self.cards.insert(i, Some(card));
+                self.drawn = i + 1;
+                self.picked_up = None;
+                return;
+            }
+        }
+
+        // Top card picked up, showing the one under it
+        if let Some(picked) = last_top.filter(|c| held == Some(*c) && top != last_top) {
+            let i = self.drawn.saturating_sub(1);
+            if i < self.cards.len() {
+                self.cards.remove(i);
+            }
+            self.drawn = i;
+            self.picked_up = Some((i, picked));
+            match top {
+                Some(card) if self.drawn > 0 => self.cards[self.drawn - 1] = Some(card),
+                Some(_) => {
+                    self.resync(top);
+                }
+                None => self.drawn = 0,
+            }
+            return;
+        }
+
+        // Face up pile turned back over
+        if !last_face_down && last_top.is_some() && face_down && top.is_none() {
+            self.drawn = 0;
+            self.len_known = true;
+            self.picked_up = None;
+            return;
+        }
+
+        // Must have drawn
+        if top.is_some() {
+            self.picked_up = None;
+            if !self.resync(top) {
+                let n = if self.len_known {
+                    3.min(self.cards.len() - self.drawn)
+                } else {
+                    3
+                };
+                self.drawn += n;
+                if self.cards.len() < self.drawn {
+                    self.cards.resize(self.drawn, None);
+                }
+                if self.drawn > 0 {
+                    self.cards[self.drawn - 1] = top;
+                }
+            }
+        }
+
+        // Out of cards so anything past here was a guess
+        if !face_down && !self.len_known && self.cards[self.drawn..].iter().all(Option::is_none) {
+            self.cards.truncate(self.drawn);
+        }
+    }
+
+    /// Moves to wherever `top` is already known to be. Returns false if it isn't known
+    fn resync(&mut self, top: Option<CardData>) -> bool {
+        match self.cards.iter().position(|c| *c == top && top.is_some()) {
+            Some(i) => {
+                self.drawn = i + 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The visible card
+    pub fn top(&self) -> Option<CardData> {
+        self.drawn.checked_sub(1).and_then(|i| self.cards[i])
+    }
+
+    /// Every card in draw order, `None` where not seen yet
+    pub fn cards(&self) -> &[Option<CardData>] {
+        &self.cards
+    }
+
+    /// How many cards are face up
+    pub fn drawn(&self) -> usize {
+        self.drawn
+    }
+
+    /// Number of cards in the pile, if known
+    pub fn len(&self) -> Option<usize> {
+        self.len_known.then_some(self.cards.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == Some(0)
+    }
+
+    /// Returns true if every card in the pile is known
+    pub fn is_complete(&self) -> bool {
+        self.len_known && self.cards.iter().all(Option::is_some)
+    }
+
+    /// The cards that will be on top after each of the next `draws` draws
+    ///
+    /// `None` where the pile is turned over or the card isn't known. Empty if the length isn't
+    /// known.
+    pub fn upcoming_tops(&self, draws: usize) -> Vec<Option<CardData>> {
+        if !self.len_known || self.cards.is_empty() {
+            return Vec::new();
+        }
+        let mut drawn = self.drawn;
+        (0..draws)
+            .map(|_| {
+                drawn = self.after_draw(drawn);
+                drawn.checked_sub(1).and_then(|i| self.cards[i])
+            })
+            .collect()
+    }
+
+    /// How many draws until `card` is on top, if it's known to be in the pile
+    ///
+    /// Turning the pile back over counts as a draw.
+    pub fn draws_until_top(&self, card: CardData) -> Option<usize> {
+        if self.top() == Some(card) {
+            return Some(0);
+        }
+        if !self.cards.contains(&Some(card)) {
+            return None;
+        }
+        // Two full cycles is enough to see every reachable card
+        let max_draws = 2 * (self.cards.len() / 3 + 2);
+        self.upcoming_tops(max_draws)
+            .into_iter()
+            .position(|c| c == Some(card))
+            .map(|i| i + 1)
+    }
+
+    /// Draws without waiting to see what comes up, for planning ahead
+    pub fn draw(&mut self) {
+        if self.len_known {
+            self.drawn = self.after_draw(self.drawn);
+        } else {
+            self.drawn += 3;
+            if self.cards.len() < self.drawn {
+                self.cards.resize(self.drawn, None);
+            }
+        }
+        self.last_top = self.top();
+    }
+
+    /// Takes the top card off without waiting to see what's under it, for planning ahead
+    pub fn take_top(&mut self) -> Option<CardData> {
+        let i = self.drawn.checked_sub(1)?;
+        let card = self.cards.remove(i);
+        self.drawn = i;
+        self.last_top = self.top();
+        card
+    }
+
+    /// Fills every card that hasn't been seen, for trying out a guess at the order
+    pub fn fill_unknown(&mut self, cards: &mut impl Iterator<Item = CardData>) {
+        for card in self.cards.iter_mut().filter(|c| c.is_none()) {
+            *card = cards.next();
+        }
+        self.last_top = self.top();
+    }
+
+    fn after_draw(&self, drawn: usize) -> usize {
+        if drawn == self.cards.len() {
+            0
+        } else {
+            (drawn + 3).min(self.cards.len())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::state::card::{Suit, Value};
+
+    use super::*;
+
+    /// A real draw pile to check the model against
+    struct Pile {
+        face_down: Vec<CardData>,
+        face_up: Vec<CardData>,
+    }
+
+    impl Pile {
+        fn new(len: u8) -> Self {
+            let face_down = (0..len)
+                .rev()
+                .map(|i| CardData {
+                    suit: Suit::Spades,
+                    value: Value::from_code(i),
+                })
+                .collect();
+            Self {
+                face_down,
+                face_up: Vec::new(),
+            }
+        }
+
+        fn draw(&mut self) {
+            if self.face_down.is_empty() {
+                self.face_down = self.face_up.drain(..).rev().collect();
+                return;
+            }
+            for _ in 0..3 {
+                if let Some(card) = self.face_down.pop() {
+                    self.face_up.push(card);
+                }
+            }
+        }
+
+        fn show(&self, model: &mut DrawPileModel, held: Option<CardData>) {
+            model.observe(
+                !self.face_down.is_empty(),
+                self.face_up.last().copied(),
+                held,
+            );
+        }
+    }
+
+    #[test]
+    fn test_cycle() {
+        let mut pile = Pile::new(11);
+        let mut model = DrawPileModel::with_len(11);
+        pile.show(&mut model, None);
+
+        // First pass, only every third card is seen
+        for _ in 0..4 {
+            pile.draw();
+            pile.show(&mut model, None);
+            assert_eq!(model.top(), pile.face_up.last().copied());
+        }
+        pile.draw();
+        pile.show(&mut model, None);
+        assert_eq!(model.len(), Some(11));
+        assert!(!model.is_complete());
+
+        // Now it knows what's coming
+        let last = CardData {
+            suit: Suit::Spades,
+            value: Value::from_code(10),
+        };
+        assert_eq!(model.draws_until_top(last), Some(4));
+        for _ in 0..4 {
+            pile.draw();
+            pile.show(&mut model, None);
+            assert_eq!(model.top(), pile.face_up.last().copied());
+        }
+        assert_eq!(model.top(), Some(last));
+    }
+
+    #[test]
+    fn test_unknown_len() {
+        let mut pile = Pile::new(12);
+        let mut model = DrawPileModel::default();
+        pile.show(&mut model, None);
+        for _ in 0..4 {
+            pile.draw();
+            pile.show(&mut model, None);
+        }
+        assert_eq!(model.len(), None);
+        pile.draw();
+        pile.show(&mut model, None);
+        assert_eq!(model.len(), Some(12));
+        for expected in model.upcoming_tops(5) {
+            pile.draw();
+            pile.show(&mut model, None);
+            assert_eq!(pile.face_up.last().copied(), expected);
+        }
+    }
+
+    #[test]
+    fn test_play_from_top() {
+        let mut pile = Pile::new(9);
+        let mut model = DrawPileModel::with_len(9);
+        pile.show(&mut model, None);
+        pile.draw();
+        pile.show(&mut model, None);
+
+        // Pick up the top card, revealing the one under it
+        let held = pile.face_up.pop();
+        pile.show(&mut model, held);
+        assert_eq!(model.top(), pile.face_up.last().copied());
+
+        // Play it somewhere then go round
+        pile.show(&mut model, None);
+        while !pile.face_down.is_empty() {
+            pile.draw();
+            pile.show(&mut model, None);
+        }
+        pile.draw();
+        pile.show(&mut model, None);
+        assert_eq!(model.len(), Some(8));
+        assert_eq!(model.cards().iter().filter(|c| c.is_some()).count(), 3);
+
+        // Every known card should be where it was predicted
+        let predicted = model.upcoming_tops(6);
+        for expected in predicted {
+            pile.draw();
+            pile.show(&mut model, None);
+            if expected.is_some() {
+                assert_eq!(pile.face_up.last().copied(), expected);
+            }
+        }
+    }
+}
@@ -1,37 +1,105 @@
+use std::time::Duration;
+
 use steamworks::SteamId;
+use tokio::time::Instant;
 
 use crate::{
-    messages::server::{GamePhase, ServerMessage},
+    cosmetics::Look,
+    events::GameEvent,
+    messages::{
+        notification::NotificationType,
+        server::{GamePhase, ServerMessage},
+    },
     position::Position,
 };
 
-use self::{card::Card, player::Player};
+use self::{
+    accounting::CardAccounting,
+    card::{Card, CardData},
+    drawpile::DrawPileModel,
+    phase::{LobbyView, PhaseView, RoundResults},
+    player::Player,
+    score::Scoreboard,
+    tracking::OpponentTracker,
+};
 
+pub mod accounting;
 pub mod card;
+pub mod drawpile;
+pub mod phase;
 pub mod player;
+pub mod score;
 pub mod stack;
+pub mod tracking;
 
 pub const ORIGIN_Y: i16 = 238;
 pub const ORIGIN_Y_FLIPPED: i16 = 1382;
 pub const STACKED_CARDS_Y_OFFSET: i16 = 32;
+/// Cards in each player's deck
+pub const DECK_SIZE: usize = 52;
+/// Cards dealt to each player's nerts pile
+pub const NERTS_PILE_SIZE: usize = 13;
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GameState {
     /// Set true when the first parsable ServerMessage has been received
     pub initialized: bool,
     pub game_phase: GamePhase,
+    /// When [GameState::game_phase] last changed
+    #[cfg_attr(feature = "serde", serde(skip, default = "Instant::now"))]
+    pub phase_started: Instant,
+    /// Whether [GameState::phase_started] is when the phase really started, not when the bot
+    /// joined part way through
+    #[cfg_attr(feature = "serde", serde(skip))]
+    phase_start_seen: bool,
+    /// How long the last intro seen from start to finish took, to count down the next one
+    #[cfg_attr(feature = "serde", serde(skip))]
+    intro_length: Option<Duration>,
+    /// Everyone in the lobby. Only has cards during [GamePhase::Play]
     pub players: Vec<Player>,
     pub center_cards: Vec<(Position, Option<Card>)>,
-    bot_player_index: usize,
+    /// Opponents' cursors and held cards over time. Not serialized, as it's all timings
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub opponents: OpponentTracker,
+    /// What the bot has seen of its own draw pile this round
+    pub draw_pile: DrawPileModel,
+    /// Which cards have been seen from each player's deck this round
+    pub accounting: CardAccounting,
+    /// Everyone's scores over the match
+    pub scoreboard: Scoreboard,
+    /// None if the bot isn't in the lobby
+    bot_player_index: Option<usize>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_steam_id"))]
     bot_steam_id: SteamId,
+    /// Cursor position sent in the last ClientMessage. Move it with
+    /// [crate::input::Action::MoveCursor]
     pub target_cursor_pos: Position,
-    pub send_left_click: bool,
-    pub send_right_click: bool,
-    pub send_make_ready: bool,
-    pub send_draw: bool,
-    pub target_card_back: u8,
-    pub target_card_color: u8,
+    /// How the bot's cards should look, sent with every ClientMessage
+    pub target_look: Look,
     pub send_key_frame: bool,
+    /// Seconds until every draw pile is shuffled, while nobody has played for a while
+    pub emergency_shuffle_countdown: Option<u8>,
+    /// How many times the draw piles have been shuffled this round
+    pub shuffle_count: u8,
+    /// The notification currently showing
+    pub notification: Option<Notification>,
+    /// Raw notification from the last message, so a new one can be told apart from the same one
+    /// still showing
+    last_notification: Option<(u64, u8)>,
+    /// Events since [GameState::take_events] was last called
+    #[cfg_attr(feature = "serde", serde(skip))]
+    events: Vec<GameEvent>,
+}
+
+/// A notification shown to everyone
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Notification {
+    pub kind: NotificationType,
+    /// Who it's about, if they're in the lobby
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_steam_id::option"))]
+    pub player: Option<SteamId>,
 }
 
 impl GameState {
@@ -39,27 +107,69 @@ impl GameState {
         Self {
             initialized: false,
             game_phase: GamePhase::Lobby,
+            phase_started: Instant::now(),
+            phase_start_seen: false,
+            intro_length: None,
             players: Vec::new(),
             center_cards: Vec::new(),
-            bot_player_index: 0,
+            opponents: OpponentTracker::default(),
+            draw_pile: DrawPileModel::default(),
+            accounting: CardAccounting::default(),
+            scoreboard: Scoreboard::default(),
+            bot_player_index: None,
             bot_steam_id: steam_id,
             target_cursor_pos: Position::zero(),
-            send_left_click: false,
-            send_right_click: false,
-            send_make_ready: false,
-            send_draw: false,
-            target_card_back: 0,
-            target_card_color: 0,
+            target_look: Look::default(),
             send_key_frame: false,
+            emergency_shuffle_countdown: None,
+            shuffle_count: 0,
+            notification: None,
+            last_notification: None,
+            events: Vec::new(),
         }
     }
 
+    /// Returns every event since this was last called
+    pub fn take_events(&mut self) -> Vec<GameEvent> {
+        std::mem::take(&mut self.events)
+    }
+
     pub fn update(&mut self, server_message: &ServerMessage) {
-        if !self.initialized {
-            self.initialized = true;
+        let initialized = self.initialized;
+        let round_started = initialized
+            && self.game_phase != GamePhase::Play
+            && server_message.game_phase == GamePhase::Play;
+        let shuffled = initialized && server_message.shuffle_count > self.shuffle_count;
+        self.initialized = true;
+        self.update_shuffle(server_message, shuffled);
+        if server_message.game_phase != self.game_phase {
+            // Joining part way through a round isn't a change
+            if initialized {
+                self.events.push(GameEvent::PhaseChanged {
+                    from: self.game_phase,
+                    to: server_message.game_phase,
+                });
+            }
+            if self.game_phase == GamePhase::Intro && self.phase_start_seen {
+                self.intro_length = Some(self.phase_elapsed());
+            }
+            self.game_phase = server_message.game_phase;
+            self.phase_started = Instant::now();
+            self.phase_start_seen = initialized;
         }
-        self.game_phase = server_message.game_phase;
+        self.players = server_message
+            .player_messages
+            .iter()
+            .map(Player::from_message)
+            .collect();
+        self.bot_player_index = self
+            .players
+            .iter()
+            .position(|p| p.steam_id == self.bot_steam_id);
+        self.scoreboard.update(&self.players);
+        self.update_notification(server_message);
         if self.game_phase != GamePhase::Play {
+            self.center_cards.clear();
             return;
         }
         self.center_cards = server_message
@@ -68,16 +178,6 @@ impl GameState {
             .map(|m| (Position::new(m.x, m.y), None))
             .collect();
         self.center_cards.sort_by_key(|(p, _)| p.x);
-        self.players = server_message
-            .player_messages
-            .iter()
-            .map(Player::from_message)
-            .collect();
-        self.bot_player_index = self
-            .players
-            .iter()
-            .position(|p| p.steam_id == self.bot_steam_id)
-            .unwrap();
 
         // If nobody playing skip
         if self.players.iter().all(|p| !p.playing) {
@@ -121,9 +221,95 @@ impl GameState {
             }
         }
 
+        self.opponents.update(
+            &self.players,
+            &self.center_cards,
+            self.bot_steam_id,
+            Instant::now(),
+        );
+
+        if round_started {
+            self.accounting.reset();
+        }
+        self.accounting.update(&self.players);
+
+        let bot = match self.bot_player_index {
+            Some(i) => &self.players[i],
+            None => return self.validate(),
+        };
+        if round_started {
+            // Nothing has been drawn yet so the draw pile is everything not dealt
+            self.draw_pile = if bot.draw_pile_up.is_none() {
+                DrawPileModel::with_len(DECK_SIZE - NERTS_PILE_SIZE - bot.table.len())
+            } else {
+                DrawPileModel::default()
+            };
+        } else if shuffled {
+            // The order is new so everything remembered is wrong
+            self.draw_pile.reset();
+        }
+        // Cards from the draw pile are only ever held on their own
+        let held = match bot.held_cards.cards.as_slice() {
+            [card] => card.data,
+            _ => None,
+        };
+        self.draw_pile.observe(
+            bot.draw_pile_down.is_some(),
+            bot.draw_pile_up.as_ref().and_then(|c| c.data),
+            held,
+        );
+
         self.validate()
     }
 
+    fn update_shuffle(&mut self, server_message: &ServerMessage, shuffled: bool) {
+        let countdown = server_message.emergency_shuffle_countdown;
+        if let (None, Some(seconds)) = (self.emergency_shuffle_countdown, countdown) {
+            self.events
+                .push(GameEvent::ShuffleCountdownStarted { seconds });
+        }
+        self.emergency_shuffle_countdown = countdown;
+        self.shuffle_count = server_message.shuffle_count;
+        if shuffled {
+            self.events.push(GameEvent::Shuffled {
+                count: self.shuffle_count,
+            });
+        }
+    }
+
+    /// Whether the draw piles are about to be shuffled because nobody is playing
+    pub fn shuffle_pending(&self) -> bool {
+        self.emergency_shuffle_countdown.is_some()
+    }
+
+    fn update_notification(&mut self, server_message: &ServerMessage) {
+        let raw = server_message
+            .notification_message
+            .as_ref()
+            .map(|n| (n.player_id, n.notification_type.code()));
+        if raw == self.last_notification {
+            return;
+        }
+        self.last_notification = raw;
+        self.notification = server_message.notification_message.as_ref().map(|n| {
+            let player = server_message
+                .player_messages
+                .iter()
+                .find(|p| p.player_id == n.player_id)
+                .map(|p| SteamId::from_raw(p.player_id));
+            Notification {
+                kind: n.notification_type,
+                player,
+            }
+        });
+        if let Some(notification) = self.notification {
+            self.events.push(GameEvent::Notification {
+                kind: notification.kind,
+                player: notification.player,
+            });
+        }
+    }
+
     pub fn validate(&self) {
         // Odd number players should be flipped
         for (i, player) in self.players.iter().filter(|p| p.playing).enumerate() {
@@ -173,27 +359,149 @@ impl GameState {
         assert!(self.center_cards.len() >= expected_centre_size);
     }
 
+    /// The bot's player. Panics if the bot isn't in the lobby, check with
+    /// [GameState::find_bot_player] first if it might not be
     pub fn bot_player(&self) -> &Player {
-        &self.players[self.bot_player_index]
+        self.find_bot_player().expect("Bot isn't in the lobby")
+    }
+
+    pub fn find_bot_player(&self) -> Option<&Player> {
+        self.bot_player_index.map(|i| &self.players[i])
+    }
+
+    /// What matters in the current phase
+    pub fn phase_view(&self) -> PhaseView<'_> {
+        match self.game_phase {
+            GamePhase::Lobby => PhaseView::Lobby(LobbyView {
+                members: &self.players,
+            }),
+            GamePhase::Intro => {
+                let elapsed = self.phase_elapsed();
+                PhaseView::Intro {
+                    elapsed,
+                    remaining: self.intro_length.map(|l| l.saturating_sub(elapsed)),
+                }
+            }
+            GamePhase::Play => PhaseView::Play,
+            GamePhase::Nerts => PhaseView::Nerts(RoundResults::from_players(&self.players)),
+        }
+    }
+
+    /// How long it's been since the phase changed
+    pub fn phase_elapsed(&self) -> Duration {
+        self.phase_started.elapsed()
     }
 
     pub fn number_playing(&self) -> usize {
         self.players.iter().filter(|p| p.playing).count()
     }
+
+    /// What this round would score for every playing player if it ended now
+    pub fn projected_scores(&self) -> Vec<(SteamId, i32)> {
+        self.players
+            .iter()
+            .filter(|p| p.playing)
+            .map(|p| (p.steam_id, p.projected_round_score()))
+            .collect()
+    }
+
+    /// The top card of a center pile, `None` if it's empty or doesn't exist
+    pub fn foundation_top(&self, i: usize) -> Option<CardData> {
+        self.center_cards.get(i)?.1.as_ref()?.data
+    }
+
+    /// Whether someone has played on `foundation` since `expected_top` was on top of it
+    pub fn foundation_changed(&self, foundation: usize, expected_top: Option<CardData>) -> bool {
+        self.foundation_top(foundation) != expected_top
+    }
+
+    /// Whether a card the bot dropped on a foundation was put back by the game, as its points
+    /// didn't go up from `points_before`
+    pub fn play_rejected(&self, points_before: u8) -> bool {
+        self.bot_player().points_cards <= points_before
+    }
+
+    /// Returns the index of the first center pile `card` can be played on
+    pub fn foundation_for(&self, card: &Card) -> Option<usize> {
+        self.foundations_for(card).next()
+    }
+
+    /// Returns the indexes of every center pile `card` can be played on
+    pub fn foundations_for<'a>(&'a self, card: &'a Card) -> impl Iterator<Item = usize> + 'a {
+        self.center_cards
+            .iter()
+            .enumerate()
+            .filter(move |(_, (_, c))| match c {
+                Some(c) => card.can_play_on(c),
+                None => card.data.is_some_and(|d| d.can_play_on_foundation(None)),
+            })
+            .map(|(i, _)| i)
+    }
+
+    /// Like [GameState::foundation_for] but skips any center pile an opponent is probably about to
+    /// play on
+    pub fn uncontested_foundation_for(&self, card: &Card, min_confidence: f32) -> Option<usize> {
+        self.foundations_for(card)
+            .find(|i| !self.opponents.is_contested(*i, min_confidence))
+    }
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use crate::messages::{
-        card::CardMessage, cardoutline::CardOutlineMessage, player::PlayerMessage,
+        card::CardMessage, cardoutline::CardOutlineMessage, notification::NotificationMessage,
+        player::PlayerMessage,
     };
 
-    use super::*;
+    use super::{
+        card::{Suit, Value},
+        *,
+    };
 
-    #[test]
-    fn test_parse_known() {
-        let mut state = GameState::new(SteamId::from_raw(76561191240930714));
-        let message = ServerMessage {
+    /// Steam id of the first player in [known_message], who isn't flipped
+    pub(crate) const KNOWN_PLAYER: u64 = 76561198064411451;
+
+    /// A face up card that's in no particular place
+    pub(crate) fn card(suit: Suit, value: Value) -> Card {
+        Card {
+            data: Some(CardData::new(suit, value)),
+            position: Position::new(0, 0),
+            face_up: true,
+            height: 0,
+            holder_index: None,
+        }
+    }
+
+    /// A player sitting in the first seat with a full nerts pile and nothing played yet
+    pub(crate) fn player_message(player_id: u64) -> PlayerMessage {
+        PlayerMessage {
+            player_id,
+            origin_x: 554,
+            origin_y: 238,
+            flipped: false,
+            is_playing: true,
+            is_ready: false,
+            can_call_nerts: false,
+            show_deck_button: false,
+            effects: 0,
+            card_color: 0,
+            tableau_count: 4,
+            called_nerts: false,
+            nerts_cards: 13,
+            holding_nerts_card: false,
+            points_cards: 0,
+            total_score: 0,
+            history_points: Vec::new(),
+            history_nertsed: Vec::new(),
+            ignore_disable_foundation: false,
+            cursor_x: 0,
+            cursor_y: 0,
+        }
+    }
+
+    /// A message from the start of a real 3 player round
+    pub(crate) fn known_message() -> ServerMessage {
+        ServerMessage {
             game_phase: GamePhase::Play,
             player_messages: vec![
                 PlayerMessage {
@@ -741,7 +1049,145 @@ mod tests {
             notification_message: None,
             emergency_shuffle_countdown: None,
             shuffle_count: 0,
-        };
+        }
+    }
+
+    #[test]
+    fn test_parse_known() {
+        let mut state = GameState::new(SteamId::from_raw(76561191240930714));
+        state.update(&known_message());
+        // Bot isn't in this game
+        assert!(state.find_bot_player().is_none());
+        assert!(matches!(state.phase_view(), PhaseView::Play));
+        assert_eq!(state.take_events(), Vec::new());
+
+        let mut message = known_message();
+        message.game_phase = GamePhase::Lobby;
+        state.update(&message);
+        assert_eq!(
+            state.take_events(),
+            vec![GameEvent::PhaseChanged {
+                from: GamePhase::Play,
+                to: GamePhase::Lobby,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_intro_countdown() {
+        let mut state = GameState::new(SteamId::from_raw(KNOWN_PLAYER));
+        let mut message = known_message();
+        // Joined part way through, so this intro can't be timed
+        message.game_phase = GamePhase::Intro;
+        state.update(&message);
+        assert!(matches!(
+            state.phase_view(),
+            PhaseView::Intro {
+                remaining: None,
+                ..
+            }
+        ));
+
+        for phase in [GamePhase::Play, GamePhase::Lobby, GamePhase::Intro] {
+            message.game_phase = phase;
+            state.update(&message);
+        }
+        state.phase_started = Instant::now() - Duration::from_secs(5);
+        for phase in [GamePhase::Play, GamePhase::Lobby, GamePhase::Intro] {
+            message.game_phase = phase;
+            state.update(&message);
+        }
+        match state.phase_view() {
+            PhaseView::Intro {
+                remaining: Some(remaining),
+                ..
+            } => assert!(remaining > Duration::from_secs(4)),
+            view => panic!("Expected a countdown, got {:?}", view),
+        }
+    }
+
+    #[test]
+    fn test_notification_once() {
+        let mut state = GameState::new(SteamId::from_raw(76561191240930714));
+        let mut message = known_message();
+        message.notification_message = Some(NotificationMessage {
+            player_id: 76561198040136714,
+            notification_type: NotificationType::new(0),
+        });
+        // Stays on screen for a few messages
+        for _ in 0..3 {
+            state.update(&message);
+        }
+        let notifications: Vec<GameEvent> = state
+            .take_events()
+            .into_iter()
+            .filter(|e| matches!(e, GameEvent::Notification { .. }))
+            .collect();
+        assert_eq!(
+            notifications,
+            vec![GameEvent::Notification {
+                kind: NotificationType::CalledNerts,
+                player: Some(SteamId::from_raw(76561198040136714)),
+            }]
+        );
+        assert!(state.notification.is_some());
+    }
+
+    #[test]
+    fn test_shuffle() {
+        let mut state = GameState::new(SteamId::from_raw(KNOWN_PLAYER));
+        let mut message = known_message();
+        message.emergency_shuffle_countdown = Some(5);
         state.update(&message);
+        // As if some of the draw pile had been seen
+        state.draw_pile = DrawPileModel::with_len(20);
+
+        message.emergency_shuffle_countdown = None;
+        message.shuffle_count = 1;
+        state.update(&message);
+        state.update(&message);
+        let shuffles = state
+            .take_events()
+            .into_iter()
+            .filter(|e| matches!(e, GameEvent::Shuffled { .. }))
+            .count();
+        assert_eq!(shuffles, 1);
+        assert_eq!(state.shuffle_count, 1);
+        assert_eq!(state.draw_pile.len(), None);
+    }
+
+    #[test]
+    fn test_lost_race() {
+        let mut state = GameState::new(SteamId::from_raw(KNOWN_PLAYER));
+        state.update(&known_message());
+        let ace = CardData::new(Suit::Diamonds, Value::Ace);
+
+        // Nobody got there first
+        state.center_cards[0].1 = Some(card(Suit::Diamonds, Value::Ace));
+        assert!(!state.foundation_changed(0, Some(ace)));
+        assert!(state.foundation_changed(0, None));
+
+        // Only seen once the points didn't go up
+        let points = state.bot_player().points_cards;
+        assert!(state.play_rejected(points));
+        state.players[0].points_cards += 1;
+        assert!(!state.play_rejected(points));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_snapshot() {
+        let json = serde_json::to_string(&known_message()).unwrap();
+        let message: ServerMessage = serde_json::from_str(&json).unwrap();
+        let mut state = GameState::new(SteamId::from_raw(76561191240930714));
+        state.update(&message);
+
+        let snapshot = serde_json::to_value(&state).unwrap();
+        assert_eq!(snapshot["players"][0]["steam_id"], 76561198064411451u64);
+        let restored: GameState = serde_json::from_value(snapshot.clone()).unwrap();
+        let restored = serde_json::to_value(&restored).unwrap();
+        for key in ["players", "center_cards", "draw_pile", "scoreboard"] {
+            assert_eq!(restored[key], snapshot[key]);
+        }
     }
 }
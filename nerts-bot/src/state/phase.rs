@@ -0,0 +1,102 @@
+use std::time::Duration;
+
+use steamworks::SteamId;
+
+use super::player::{round_score, Player};
+
+/// What matters about the game in its current phase, from [super::GameState::phase_view]
+#[derive(Debug, Clone)]
+pub enum PhaseView<'a> {
+    /// Waiting for everyone to ready up
+    Lobby(LobbyView<'a>),
+    /// Counting down before the cards are dealt
+    Intro {
+        /// How long the intro has been going
+        elapsed: Duration,
+        /// Roughly how long until the cards are dealt, once a whole intro has been seen to time it
+        remaining: Option<Duration>,
+    },
+    /// The round is being played, everything is in [super::GameState]
+    Play,
+    /// Someone called nerts and the round's results are showing
+    Nerts(RoundResults),
+}
+
+/// Everyone in the lobby
+#[derive(Debug, Clone)]
+pub struct LobbyView<'a> {
+    pub members: &'a [Player],
+}
+
+impl LobbyView<'_> {
+    /// Members who will be dealt in next round
+    pub fn playing(&self) -> impl Iterator<Item = &Player> {
+        self.members.iter().filter(|p| p.playing)
+    }
+
+    /// Members who have readied up
+    pub fn ready(&self) -> impl Iterator<Item = &Player> {
+        self.members.iter().filter(|p| p.ready)
+    }
+
+    /// Whether every playing member is ready
+    pub fn all_ready(&self) -> bool {
+        self.playing().all(|p| p.ready)
+    }
+}
+
+/// How one player did in the round that just finished
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RoundOutcome {
+    pub steam_id: SteamId,
+    /// Cards played on foundations
+    pub points_cards: u8,
+    /// Cards left in the nerts pile
+    pub nerts_left: u8,
+    pub called_nerts: bool,
+}
+
+impl RoundOutcome {
+    pub fn from_player(player: &Player) -> Self {
+        Self {
+            steam_id: player.steam_id,
+            points_cards: player.points_cards,
+            nerts_left: player.nerts_count,
+            called_nerts: player.called_nerts,
+        }
+    }
+
+    /// Points scored this round
+    pub fn points(&self) -> i32 {
+        round_score(self.points_cards, self.nerts_left)
+    }
+}
+
+/// Results of the round that just finished, best first
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoundResults {
+    pub outcomes: Vec<RoundOutcome>,
+}
+
+impl RoundResults {
+    pub fn from_players(players: &[Player]) -> Self {
+        let mut outcomes: Vec<RoundOutcome> = players
+            .iter()
+            .filter(|p| p.playing)
+            .map(RoundOutcome::from_player)
+            .collect();
+        outcomes.sort_by_key(|o| std::cmp::Reverse(o.points()));
+        Self { outcomes }
+    }
+
+    pub fn nerts_caller(&self) -> Option<SteamId> {
+        self.outcomes
+            .iter()
+            .find(|o| o.called_nerts)
+            .map(|o| o.steam_id)
+    }
+
+    pub fn outcome(&self, steam_id: SteamId) -> Option<&RoundOutcome> {
+        self.outcomes.iter().find(|o| o.steam_id == steam_id)
+    }
+}
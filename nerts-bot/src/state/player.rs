@@ -6,20 +6,39 @@ use crate::{messages::player::PlayerMessage, position::Position};
 use super::{card::Card, stack::PlayedStack};
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Player {
     pub cursor: Position,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_steam_id"))]
     pub steam_id: SteamId,
     pub playing: bool,
     pub ready: bool,
     pub origin: Position,
     pub flipped: bool,
+    /// Colour of the back of their cards
+    pub card_color: u8,
     pub nerts_cards: Vec<Card>,
+    /// Cards left in the nerts pile, known even when the cards themselves aren't sent
+    pub nerts_count: u8,
     pub draw_pile_down: Option<Card>,
     pub draw_pile_up: Option<Card>,
     pub table: Vec<PlayedStack>,
     pub held_cards: PlayedStack,
     pub can_call_nerts: bool,
     pub called_nerts: bool,
+    /// Cards played on foundations this round
+    pub points_cards: u8,
+    /// Score over the match, not counting this round
+    pub total_score: i16,
+    /// Points from each finished round
+    pub history_points: Vec<i8>,
+    /// Whether the player called nerts in each finished round
+    pub history_nertsed: Vec<bool>,
+}
+
+/// Points for a round, one per foundation card minus two per card left in the nerts pile
+pub fn round_score(points_cards: u8, nerts_left: u8) -> i32 {
+    points_cards as i32 - 2 * nerts_left as i32
 }
 
 impl Player {
@@ -32,7 +51,9 @@ impl Player {
             ready: message.is_ready,
             origin: Position::new(message.origin_x, message.origin_y),
             flipped: message.flipped,
+            card_color: message.card_color,
             nerts_cards: Vec::new(),
+            nerts_count: message.nerts_cards,
             draw_pile_down: None,
             draw_pile_up: None,
             table: (0..message.tableau_count)
@@ -41,9 +62,18 @@ impl Player {
             held_cards: PlayedStack::default(),
             can_call_nerts: message.can_call_nerts,
             called_nerts: message.called_nerts,
+            points_cards: message.points_cards,
+            total_score: message.total_score,
+            history_points: message.history_points.clone(),
+            history_nertsed: message.history_nertsed.clone(),
         }
     }
 
+    /// What this round would score if it ended now
+    pub fn projected_round_score(&self) -> i32 {
+        round_score(self.points_cards, self.nerts_count)
+    }
+
     pub fn owns_card(&self, card: &Card, player_index: usize) -> bool {
         // Bounds are just roughly taken from putting mouse in top right corner
         card.holder_index == Some(player_index)
@@ -0,0 +1,144 @@
+use steamworks::SteamId;
+
+use super::player::Player;
+
+/// How one player did in a finished round
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RoundResult {
+    pub points: i8,
+    /// Whether they called nerts
+    pub nertsed: bool,
+}
+
+/// One player's scores over the match
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScoreEntry {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_steam_id"))]
+    pub steam_id: SteamId,
+    pub total_score: i16,
+    pub rounds: Vec<RoundResult>,
+    /// False once they've left the lobby
+    pub present: bool,
+}
+
+/// Every player's scores over the match, kept across rounds
+///
+/// Players who leave are kept so the match can be reported in full. Starts over when a player's
+/// history gets shorter, as that only happens when a new match starts.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Scoreboard {
+    entries: Vec<ScoreEntry>,
+}
+
+impl Scoreboard {
+    pub fn reset(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn update(&mut self, players: &[Player]) {
+        let new_match = players.iter().any(|p| {
+            self.entry(p.steam_id)
+                .is_some_and(|e| p.history_points.len() < e.rounds.len())
+        });
+        if new_match {
+            self.reset();
+        }
+
+        for entry in self.entries.iter_mut() {
+            entry.present = false;
+        }
+        for player in players {
+            let rounds = player
+                .history_points
+                .iter()
+                .zip(player.history_nertsed.iter())
+                .map(|(points, nertsed)| RoundResult {
+                    points: *points,
+                    nertsed: *nertsed,
+                })
+                .collect();
+            let entry = ScoreEntry {
+                steam_id: player.steam_id,
+                total_score: player.total_score,
+                rounds,
+                present: true,
+            };
+            match self
+                .entries
+                .iter_mut()
+                .find(|e| e.steam_id == player.steam_id)
+            {
+                Some(existing) => *existing = entry,
+                None => self.entries.push(entry),
+            }
+        }
+    }
+
+    pub fn entry(&self, steam_id: SteamId) -> Option<&ScoreEntry> {
+        self.entries.iter().find(|e| e.steam_id == steam_id)
+    }
+
+    pub fn entries(&self) -> &[ScoreEntry] {
+        &self.entries
+    }
+
+    /// Number of finished rounds in the match
+    pub fn rounds_played(&self) -> usize {
+        self.entries
+            .iter()
+            .map(|e| e.rounds.len())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Every player ordered by total score, highest first
+    pub fn standings(&self) -> Vec<&ScoreEntry> {
+        let mut standings: Vec<&ScoreEntry> = self.entries.iter().collect();
+        standings.sort_by_key(|e| std::cmp::Reverse(e.total_score));
+        standings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{messages::player::PlayerMessage, state::tests::player_message};
+
+    use super::*;
+
+    fn player(id: u64, history_points: Vec<i8>) -> Player {
+        Player::from_message(&PlayerMessage {
+            total_score: history_points.iter().map(|p| *p as i16).sum(),
+            history_nertsed: history_points.iter().map(|p| *p > 10).collect(),
+            history_points,
+            ..player_message(id)
+        })
+    }
+
+    #[test]
+    fn test_rounds() {
+        let mut scoreboard = Scoreboard::default();
+        scoreboard.update(&[player(1, vec![18]), player(2, vec![-4])]);
+        scoreboard.update(&[player(1, vec![18, 3])]);
+        assert_eq!(scoreboard.rounds_played(), 2);
+        let left = scoreboard.entry(SteamId::from_raw(2)).unwrap();
+        assert!(!left.present);
+        assert_eq!(left.total_score, -4);
+        let standings = scoreboard.standings();
+        assert_eq!(standings[0].steam_id, SteamId::from_raw(1));
+        assert_eq!(
+            standings[0].rounds[0],
+            RoundResult {
+                points: 18,
+                nertsed: true,
+            }
+        );
+
+        // New match
+        scoreboard.update(&[player(1, Vec::new())]);
+        assert_eq!(scoreboard.entries().len(), 1);
+        assert_eq!(scoreboard.rounds_played(), 0);
+    }
+}
@@ -1,6 +1,7 @@
 use super::card::Card;
 
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PlayedStack {
     pub cards: Vec<Card>,
 }
@@ -16,6 +17,17 @@ impl PlayedStack {
         //     .all(|(a, b)| a.position.y > b.position.y))
     }
 
+    /// The card on top, the only one more cards can go on
+    pub fn top(&self) -> Option<&Card> {
+        self.cards.first()
+    }
+
+    /// The card at the bottom. For held cards this is the one that was picked up, which has to
+    /// fit wherever they're put down
+    pub fn bottom(&self) -> Option<&Card> {
+        self.cards.last()
+    }
+
     pub fn add_card(&mut self, card: Card) {
         self.cards.push(card);
     }
@@ -0,0 +1,320 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    time::Duration,
+};
+
+use steamworks::SteamId;
+use tokio::time::Instant;
+
+use crate::position::Position;
+
+use super::{
+    card::{Card, CardData},
+    player::Player,
+};
+
+/// How many cursor positions to remember per player, one per update
+const HISTORY_LEN: usize = 8;
+/// Velocity is worked out over at most this long
+const VELOCITY_WINDOW: Duration = Duration::from_millis(300);
+/// Offset from a center pile's position to roughly the middle of the card
+const CARD_CENTER: Position = Position::new(60, 90);
+/// Distance in pixels at which being close to a foundation counts for a lot less
+const PROXIMITY_SCALE: f32 = 400.0;
+
+/// Follows every opponent's cursor and held card to guess which foundation they're about to play on
+#[derive(Debug, Clone, Default)]
+pub struct OpponentTracker {
+    tracks: HashMap<SteamId, PlayerTrack>,
+    predictions: Vec<Prediction>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PlayerTrack {
+    cursor_history: VecDeque<(Instant, Position)>,
+    held: Option<CardData>,
+    held_since: Option<Instant>,
+}
+
+impl PlayerTrack {
+    pub fn cursor(&self) -> Option<Position> {
+        self.cursor_history.back().map(|(_, p)| *p)
+    }
+
+    /// Cursor velocity in pixels per second
+    pub fn velocity(&self) -> (f32, f32) {
+        let (last_time, last) = match self.cursor_history.back() {
+            Some(last) => *last,
+            None => return (0.0, 0.0),
+        };
+        let first = self
+            .cursor_history
+            .iter()
+            .find(|(t, _)| last_time.duration_since(*t) <= VELOCITY_WINDOW);
+        match first {
+            Some((first_time, first)) if *first_time < last_time => {
+                let secs = last_time.duration_since(*first_time).as_secs_f32();
+                (
+                    (last.x as i32 - first.x as i32) as f32 / secs,
+                    (last.y as i32 - first.y as i32) as f32 / secs,
+                )
+            }
+            _ => (0.0, 0.0),
+        }
+    }
+
+    /// The single card the player is holding, if any
+    pub fn held(&self) -> Option<CardData> {
+        self.held
+    }
+
+    /// How long the current card has been held
+    pub fn held_for(&self, now: Instant) -> Option<Duration> {
+        self.held_since.map(|t| now.duration_since(t))
+    }
+}
+
+/// A guess that a player is about to play on a foundation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Prediction {
+    pub steam_id: SteamId,
+    /// Index into [super::GameState::center_cards]
+    pub foundation: usize,
+    pub card: CardData,
+    /// From 0 to 1
+    pub confidence: f32,
+    /// Roughly how long until they get there, if they're heading towards it
+    pub eta: Option<Duration>,
+}
+
+impl OpponentTracker {
+    /// Records the latest cursor and held card of every opponent and updates the predictions
+    pub fn update(
+        &mut self,
+        players: &[Player],
+        center_cards: &[(Position, Option<Card>)],
+        bot_steam_id: SteamId,
+        now: Instant,
+    ) {
+        self.tracks
+            .retain(|id, _| players.iter().any(|p| p.steam_id == *id));
+        self.predictions.clear();
+
+        for player in players
+            .iter()
+            .filter(|p| p.playing && p.steam_id != bot_steam_id)
+        {
+            let track = self.tracks.entry(player.steam_id).or_default();
+            // Kept even when the cursor hasn't moved, so a stopped cursor has no velocity
+            track.cursor_history.push_back((now, player.cursor));
+            if track.cursor_history.len() > HISTORY_LEN {
+                track.cursor_history.pop_front();
+            }
+
+            // Only single cards go on foundations
+            let held = match player.held_cards.cards.as_slice() {
+                [card] => card.data,
+                _ => None,
+            };
+            if held != track.held {
+                track.held = held;
+                track.held_since = held.map(|_| now);
+            }
+
+            if let Some(card) = held {
+                let predictions = Self::predict(player.steam_id, track, card, center_cards);
+                self.predictions.extend(predictions);
+            }
+        }
+    }
+
+    fn predict(
+        steam_id: SteamId,
+        track: &PlayerTrack,
+        card: CardData,
+        center_cards: &[(Position, Option<Card>)],
+    ) -> Vec<Prediction> {
+        let cursor = match track.cursor() {
+            Some(cursor) => cursor,
+            None => return Vec::new(),
+        };
+        let (vx, vy) = track.velocity();
+        let speed = (vx * vx + vy * vy).sqrt();
+
+        // Every foundation the card can go on is likely if the cursor is close to it or heading
+        // towards it. Each is judged on its own, so holding a card that only fits one foundation
+        // doesn't make it contested
+        center_cards
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, top))| match top {
+                Some(top) => top
+                    .data
+                    .is_some_and(|top| card.can_play_on_foundation(Some(&top))),
+                None => card.can_play_on_foundation(None),
+            })
+            .map(|(i, (pos, _))| {
+                let target = *pos + CARD_CENTER;
+                let distance = cursor.distance(target).max(1.0);
+                let heading = if speed > 0.0 {
+                    let (dx, dy) = (
+                        (target.x as i32 - cursor.x as i32) as f32 / distance,
+                        (target.y as i32 - cursor.y as i32) as f32 / distance,
+                    );
+                    ((vx * dx + vy * dy) / speed).max(0.0)
+                } else {
+                    0.0
+                };
+                let eta = (heading > 0.5)
+                    .then(|| Duration::from_secs_f32(distance / (speed * heading).max(1.0)));
+                let proximity = (-distance / PROXIMITY_SCALE).exp();
+                Prediction {
+                    steam_id,
+                    foundation: i,
+                    card,
+                    confidence: 1.0 - (1.0 - proximity) * (1.0 - heading),
+                    eta,
+                }
+            })
+            .collect()
+    }
+
+    pub fn track(&self, steam_id: SteamId) -> Option<&PlayerTrack> {
+        self.tracks.get(&steam_id)
+    }
+
+    /// Every current guess, for every opponent holding a card that can go on a foundation
+    pub fn predictions(&self) -> &[Prediction] {
+        &self.predictions
+    }
+
+    /// The most likely opponent to play on a foundation next
+    pub fn contender(&self, foundation: usize) -> Option<&Prediction> {
+        self.predictions
+            .iter()
+            .filter(|p| p.foundation == foundation)
+            .max_by(|a, b| a.confidence.total_cmp(&b.confidence))
+    }
+
+    /// Returns true if an opponent is probably about to play on a foundation
+    pub fn is_contested(&self, foundation: usize, min_confidence: f32) -> bool {
+        self.contender(foundation)
+            .is_some_and(|p| p.confidence >= min_confidence)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        messages::{card::CardMessage, player::PlayerMessage},
+        state::{
+            card::{Suit, Value},
+            tests::player_message,
+        },
+    };
+
+    use super::*;
+
+    fn opponent(cursor: Position, held: u8) -> Player {
+        let mut player = Player::from_message(&PlayerMessage {
+            cursor_x: cursor.x,
+            cursor_y: cursor.y,
+            ..player_message(1)
+        });
+        player.held_cards.add_card(Card::from_message(&CardMessage {
+            x: cursor.x,
+            y: cursor.y,
+            data: held,
+            flags: 1,
+            height: 0,
+            holder: 0,
+        }));
+        player
+    }
+
+    fn center(x: i16, data: u8) -> (Position, Option<Card>) {
+        let pos = Position::new(x, 1102);
+        let card = Card::from_message(&CardMessage {
+            x: pos.x,
+            y: pos.y,
+            data,
+            flags: 1,
+            height: 0,
+            holder: 255,
+        });
+        (pos, Some(card))
+    }
+
+    #[test]
+    fn test_predicts_heading() {
+        // Two four of hearts, one far left and one far right
+        let center_cards = vec![center(967, 29), center(1607, 1), center(2727, 29)];
+        let mut tracker = OpponentTracker::default();
+        let start = Instant::now();
+        let bot_id = SteamId::from_raw(2);
+
+        // Holding the five of hearts, moving right
+        for (i, x) in [1700, 1800, 1900].into_iter().enumerate() {
+            let players = vec![opponent(Position::new(x, 1000), 30)];
+            let now = start + Duration::from_millis(50 * i as u64);
+            tracker.update(&players, &center_cards, bot_id, now);
+        }
+
+        let track = tracker.track(SteamId::from_raw(1)).unwrap();
+        assert_eq!(
+            track.held(),
+            Some(CardData {
+                suit: Suit::Hearts,
+                value: Value::Five,
+            })
+        );
+        assert!(track.velocity().0 > 0.0);
+        assert_eq!(tracker.predictions().len(), 2);
+        assert!(tracker.is_contested(2, 0.6));
+        assert!(!tracker.is_contested(0, 0.6));
+        assert!(tracker.contender(2).unwrap().eta.is_some());
+        assert!(tracker.contender(1).is_none());
+    }
+
+    #[test]
+    fn test_far_and_leaving() {
+        // Only the right four of hearts is left, the five of hearts can't go anywhere else
+        let center_cards = vec![center(967, 1), center(2727, 29)];
+        let mut tracker = OpponentTracker::default();
+        let start = Instant::now();
+        let bot_id = SteamId::from_raw(2);
+
+        // Moving left, away from it
+        for (i, x) in [1300, 1200, 1100].into_iter().enumerate() {
+            let players = vec![opponent(Position::new(x, 1000), 30)];
+            let now = start + Duration::from_millis(50 * i as u64);
+            tracker.update(&players, &center_cards, bot_id, now);
+        }
+
+        assert_eq!(tracker.predictions().len(), 1);
+        assert!(tracker.contender(1).unwrap().confidence < 0.1);
+        assert!(!tracker.is_contested(1, 0.6));
+    }
+    #[test]
+    fn test_stopped() {
+        let center_cards = vec![center(967, 1), center(2727, 29)];
+        let mut tracker = OpponentTracker::default();
+        let start = Instant::now();
+        let bot_id = SteamId::from_raw(2);
+
+        // Heading right then stopping well short of it
+        for (i, x) in [1700, 1800, 1900, 1900, 1900, 1900, 1900, 1900, 1900]
+            .into_iter()
+            .enumerate()
+        {
+            let players = vec![opponent(Position::new(x, 1000), 30)];
+            let now = start + Duration::from_millis(50 * i as u64);
+            tracker.update(&players, &center_cards, bot_id, now);
+        }
+
+        let track = tracker.track(SteamId::from_raw(1)).unwrap();
+        assert_eq!(track.velocity(), (0.0, 0.0));
+        assert!(tracker.contender(1).unwrap().eta.is_none());
+        assert!(!tracker.is_contested(1, 0.6));
+    }
+}
@@ -0,0 +1,139 @@
+use crate::{
+    difficulty::DifficultySettings,
+    state::{card::CardData, player::Player, GameState},
+};
+
+use super::{
+    held_decision, stall_decision, Decision, Source, Strategy, Target, CONTESTED_CONFIDENCE,
+};
+
+/// Plays the first card it finds that can go on a foundation, otherwise draws
+///
+/// Every decision it tries to do one of the following, starting from the top:
+///   - Call nerts
+///   - If holding a card play it if possible, drop it if not
+///   - If the draw piles are about to be shuffled, play anything that can go on a foundation
+///   - Find a card that can be played on a foundation no opponent is about to play on and move it
+///     there
+///   - Move the top nerts card onto a table stack it can go on, or an empty space. Only with a
+///     search depth of 2 or more
+///   - Move a table card onto another stack if that opens a space or uncovers a card the top
+///     nerts card can go on. Only with a search depth of 3 or more
+///   - If nothing else, draw from the deck
+#[derive(Debug, Default)]
+pub struct GreedyStrategy;
+
+impl Strategy for GreedyStrategy {
+    fn name(&self) -> &'static str {
+        "greedy"
+    }
+
+    fn decide(&mut self, state: &GameState, difficulty: &DifficultySettings) -> Decision {
+        let player = state.bot_player();
+
+        if player.can_call_nerts {
+            return Decision::CallNerts;
+        }
+
+        // If holding a card either play it or drop it
+        if let Some(decision) = held_decision(state) {
+            return decision;
+        }
+
+        // Play anything before the draw piles get shuffled
+        if let Some(decision) = stall_decision(state) {
+            return decision;
+        }
+
+        // Find a card we can play
+        let sources = std::iter::once(Source::Nerts)
+            .chain((0..player.table.len()).map(Source::Table))
+            .chain(std::iter::once(Source::DrawPile));
+        for source in sources {
+            let card = match source.card(player) {
+                Some(card) if card.face_up && card.data.is_some() => card,
+                _ => continue,
+            };
+            // Don't race opponents for a foundation they're about to take
+            if let Some(i) = state.uncontested_foundation_for(card, CONTESTED_CONFIDENCE) {
+                return Decision::Move {
+                    from: source,
+                    to: Target::Foundation(i),
+                };
+            }
+        }
+
+        let nerts_top = Source::Nerts.card(player).and_then(|c| c.visible_data());
+        if let Some(nerts_top) = nerts_top {
+            // If possible, play from nerts pile onto table
+            if difficulty.search_depth >= 2 {
+                if let Some(i) = table_stack_for(player, nerts_top) {
+                    return Decision::Move {
+                        from: Source::Nerts,
+                        to: Target::Table(i),
+                    };
+                }
+            }
+
+            // Otherwise try and make somewhere for it to go
+            if difficulty.search_depth >= 3 {
+                if let Some(decision) = unblock(player, nerts_top) {
+                    return decision;
+                }
+            }
+        }
+
+        Decision::Draw
+    }
+}
+
+/// Returns the table stack `card` can go on, preferring stacks over empty spaces
+fn table_stack_for(player: &Player, card: CardData) -> Option<usize> {
+    let top = |i: usize| player.table[i].cards.first().and_then(|c| c.visible_data());
+    (0..player.table.len())
+        .find(|i| top(*i).is_some_and(|top| card.can_play_on_table(Some(&top))))
+        .or_else(|| (0..player.table.len()).find(|i| player.table[*i].cards.is_empty()))
+}
+
+/// Finds table cards to move onto another stack so the top nerts card has somewhere to go
+///
+/// Either a card is moved with everything on it, or just the top one. The stack has to be
+/// moved whole, leaving an empty space, or the card under it has to be one the top nerts card
+/// can go on.
+fn unblock(player: &Player, nerts_top: CardData) -> Option<Decision> {
+    for (from, stack) in player.table.iter().enumerate() {
+        for (depth, card) in stack.cards.iter().enumerate() {
+            let card = match card.visible_data() {
+                Some(card) => card,
+                None => break,
+            };
+            let opens = match stack.cards.get(depth + 1) {
+                None => true,
+                Some(under) => under
+                    .visible_data()
+                    .is_some_and(|under| nerts_top.can_play_on_table(Some(&under))),
+            };
+            if !opens {
+                continue;
+            }
+            let to = player.table.iter().enumerate().position(|(i, s)| {
+                i != from
+                    && s.cards
+                        .first()
+                        .and_then(|c| c.visible_data())
+                        .is_some_and(|top| card.can_play_on_table(Some(&top)))
+            });
+            if let Some(to) = to {
+                let from = match depth {
+                    0 => Source::Table(from),
+                    _ => Source::Stack(from, depth),
+                };
+                return Some(Decision::Move {
+                    from,
+                    to: Target::Table(to),
+                });
+            }
+        }
+    }
+    None
+}
@@ -0,0 +1,242 @@
+use crate::{
+    difficulty::DifficultySettings,
+    position::Position,
+    state::{card::Card, player::Player, GameState},
+};
+
+pub mod greedy;
+pub mod montecarlo;
+pub mod planner;
+pub mod sim;
+
+/// Name of every strategy, for [from_name]
+pub const NAMES: [&str; 3] = ["greedy", "planner", "montecarlo"];
+
+/// Creates the strategy called `name` with its default settings
+pub fn from_name(name: &str) -> Option<Box<dyn Strategy>> {
+    match name.to_ascii_lowercase().as_str() {
+        "greedy" => Some(Box::new(greedy::GreedyStrategy)),
+        "planner" => Some(Box::new(planner::PlannerStrategy::default())),
+        "montecarlo" => Some(Box::new(montecarlo::MonteCarloStrategy::default())),
+        _ => None,
+    }
+}
+
+/// How sure we need to be that an opponent is going for a foundation before giving it up
+pub const CONTESTED_CONFIDENCE: f32 = 0.6;
+
+/// Somewhere on the bot's side of the table a card can be picked up from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    /// Top of the nerts pile
+    Nerts,
+    /// Face up card on the draw pile
+    DrawPile,
+    /// Top card of a table stack
+    Table(usize),
+    /// The card `depth` cards under the top of a table stack, picked up along with every card on it
+    Stack(usize, usize),
+}
+
+impl Source {
+    /// Returns the card that would be picked up
+    pub fn card<'a>(&self, player: &'a Player) -> Option<&'a Card> {
+        match *self {
+            Source::Nerts => player.nerts_cards.first(),
+            Source::DrawPile => player.draw_pile_up.as_ref(),
+            Source::Table(i) => player.table.get(i).and_then(|s| s.cards.first()),
+            Source::Stack(i, depth) => player.table.get(i).and_then(|s| s.cards.get(depth)),
+        }
+    }
+}
+
+/// Somewhere a held card can be played
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    /// One of the shared center piles, indexing [GameState::center_cards]
+    Foundation(usize),
+    /// One of the bot's table stacks
+    Table(usize),
+}
+
+impl Target {
+    /// Returns where to click to play a card here
+    pub fn position(&self, state: &GameState) -> Option<Position> {
+        match *self {
+            Target::Foundation(i) => state.center_cards.get(i).map(|(p, _)| *p),
+            Target::Table(i) => {
+                let player = state.bot_player();
+                match player.table.get(i)?.cards.first() {
+                    Some(top) => Some(top.position),
+                    None => player.table_base_positions().get(i).copied(),
+                }
+            }
+        }
+    }
+}
+
+/// What the bot should do next
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    /// Ready up while waiting for a round to start
+    Ready,
+    CallNerts,
+    /// Pick up a card and play it somewhere
+    Move {
+        from: Source,
+        to: Target,
+    },
+    /// Play the card currently held
+    PlayHeld(Target),
+    /// Put the held card back where it came from
+    DropHeld,
+    Draw,
+    /// Nothing worth doing until something changes
+    Wait,
+}
+
+/// Decides what the bot does while playing
+///
+/// Only asked for decisions during [crate::messages::server::GamePhase::Play].
+pub trait Strategy: Send {
+    fn name(&self) -> &'static str;
+
+    fn decide(&mut self, state: &GameState, difficulty: &DifficultySettings) -> Decision;
+}
+
+/// Plays the held card on a foundation if it can go on one, or a held stack on another table
+/// stack, otherwise drops it. `None` if not holding anything
+pub fn held_decision(state: &GameState) -> Option<Decision> {
+    let player = state.bot_player();
+    let held = &player.held_cards;
+    let card = held.bottom()?;
+    let target = if held.cards.len() == 1 {
+        state.foundation_for(card).map(Target::Foundation)
+    } else {
+        // Putting a stack in an empty space does nothing, it's only ever come from the table
+        (0..player.table.len())
+            .filter(|&i| !player.table[i].cards.is_empty())
+            .map(Target::Table)
+            .find(|&t| held_fits(state, t))
+    };
+    Some(target.map_or(Decision::DropHeld, Decision::PlayHeld))
+}
+
+/// Whether the held cards can be put down on `target`
+///
+/// Only single cards go on foundations. Stacks go on the table by the card at their bottom.
+pub fn held_fits(state: &GameState, target: Target) -> bool {
+    let player = state.bot_player();
+    let held = &player.held_cards;
+    let bottom = match held.bottom() {
+        Some(card) => card,
+        None => return false,
+    };
+    match target {
+        Target::Foundation(i) => {
+            held.cards.len() == 1 && state.foundations_for(bottom).any(|f| f == i)
+        }
+        Target::Table(i) => {
+            let (bottom, stack) = match (bottom.visible_data(), player.table.get(i)) {
+                (Some(bottom), Some(stack)) => (bottom, stack),
+                _ => return false,
+            };
+            let top = stack.top().and_then(|c| c.visible_data());
+            (top.is_some() || stack.cards.is_empty()) && bottom.can_play_on_table(top.as_ref())
+        }
+    }
+}
+
+/// While the draw piles are about to be shuffled, plays the first card that can go on any
+/// foundation, even one an opponent is going for. `None` if no shuffle is pending or nothing can
+/// be played
+///
+/// Playing anything stops the shuffle, which would throw away everything known about the draw
+/// pile.
+pub fn stall_decision(state: &GameState) -> Option<Decision> {
+    if !state.shuffle_pending() {
+        return None;
+    }
+    let player = state.bot_player();
+    let sources = std::iter::once(Source::Nerts)
+        .chain((0..player.table.len()).map(Source::Table))
+        .chain(std::iter::once(Source::DrawPile));
+    sources
+        .filter_map(|source| {
+            let card = source.card(player).filter(|c| c.face_up)?;
+            let i = state.foundation_for(card)?;
+            Some(Decision::Move {
+                from: source,
+                to: Target::Foundation(i),
+            })
+        })
+        .next()
+}
+
+#[cfg(test)]
+mod tests {
+    use steamworks::SteamId;
+
+    use crate::state::{
+        card::{CardData, Suit, Value},
+        stack::PlayedStack,
+        tests::{card, known_message, KNOWN_PLAYER},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_stall() {
+        let mut state = GameState::new(SteamId::from_raw(KNOWN_PLAYER));
+        state.update(&known_message());
+        // An ace on top of the first table stack
+        state.players[0].table[0].cards[0].data = Some(CardData::new(Suit::Diamonds, Value::Ace));
+        assert_eq!(stall_decision(&state), None);
+
+        state.emergency_shuffle_countdown = Some(5);
+        assert_eq!(
+            stall_decision(&state),
+            Some(Decision::Move {
+                from: Source::Table(0),
+                to: Target::Foundation(0),
+            })
+        );
+    }
+
+    #[test]
+    fn test_held_stack() {
+        let mut state = GameState::new(SteamId::from_raw(KNOWN_PLAYER));
+        state.update(&known_message());
+        // The nine of hearts with the eight of spades on it. The last table stack is the ten of
+        // spades
+        state.players[0].held_cards = PlayedStack {
+            cards: vec![
+                card(Suit::Spades, Value::Eight),
+                card(Suit::Hearts, Value::Nine),
+            ],
+        };
+        assert!(held_fits(&state, Target::Table(4)));
+        assert!(!held_fits(&state, Target::Table(1)));
+        assert!(!held_fits(&state, Target::Foundation(0)));
+        assert_eq!(
+            held_decision(&state),
+            Some(Decision::PlayHeld(Target::Table(4)))
+        );
+
+        // Fits in an empty space, but there's no point putting it there
+        state.players[0].table[0].cards.clear();
+        state.players[0].table[4].cards[0] = card(Suit::Clubs, Value::Two);
+        assert!(held_fits(&state, Target::Table(0)));
+        assert_eq!(held_decision(&state), Some(Decision::DropHeld));
+
+        // Single cards go on foundations
+        state.players[0].held_cards = PlayedStack {
+            cards: vec![card(Suit::Hearts, Value::Ace)],
+        };
+        assert!(held_fits(&state, Target::Foundation(0)));
+        assert_eq!(
+            held_decision(&state),
+            Some(Decision::PlayHeld(Target::Foundation(0)))
+        );
+    }
+}
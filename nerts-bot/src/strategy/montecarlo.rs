@@ -0,0 +1,236 @@
+use std::time::{Duration, Instant};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    difficulty::{DifficultySettings, MAX_SEARCH_DEPTH},
+    state::{
+        card::{CardData, Suit, Value},
+        GameState,
+    },
+};
+
+use super::{held_decision, sim::Board, stall_decision, Decision, Strategy};
+
+/// How opponents are assumed to play while simulating
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OpponentModel {
+    /// Opponents never play, so only the hidden cards are random
+    Idle,
+    /// Every time the bot moves, each opponent has this chance of playing on a random foundation
+    Random { play_chance: f32 },
+}
+
+impl Default for OpponentModel {
+    fn default() -> Self {
+        OpponentModel::Random { play_chance: 0.3 }
+    }
+}
+
+impl OpponentModel {
+    /// Lets every opponent have a go at the foundations
+    fn play(&self, board: &mut Board, opponents: usize, rng: &mut impl Rng) {
+        let play_chance = match *self {
+            OpponentModel::Idle => return,
+            OpponentModel::Random { play_chance } => play_chance,
+        };
+        if board.foundations.is_empty() {
+            return;
+        }
+        for _ in 0..opponents {
+            if rng.gen::<f32>() >= play_chance {
+                continue;
+            }
+            let i = rng.gen_range(0..board.foundations.len());
+            let foundation = &mut board.foundations[i];
+            *foundation = match *foundation {
+                Some(top) if top.value == Value::King => continue,
+                Some(top) => Some(CardData::new(
+                    top.suit,
+                    Value::from_code(top.value.as_u8() + 1),
+                )),
+                None => {
+                    let suit = [Suit::Clubs, Suit::Diamonds, Suit::Hearts, Suit::Spades]
+                        [rng.gen_range(0..4)];
+                    Some(CardData::new(suit, Value::Ace))
+                }
+            };
+        }
+    }
+}
+
+/// Picks the move with the best expected round score over many guesses at the hidden cards
+///
+/// Each sample shuffles the cards the bot hasn't seen into its face down nerts and draw pile
+/// cards, then plays every possible move out for `rollout_moves` moves with opponents playing by
+/// `opponents`. Both are for the deepest [DifficultySettings::search_depth] and are cut down in
+/// proportion for shallower ones.
+#[derive(Debug)]
+pub struct MonteCarloStrategy {
+    /// Maximum number of guesses at the hidden cards per decision
+    pub samples: u32,
+    /// How many moves each guess is played out for
+    pub rollout_moves: u32,
+    pub opponents: OpponentModel,
+    /// Stops sampling once this much time has been spent on a decision
+    pub time_budget: Duration,
+    rng: StdRng,
+}
+
+impl Default for MonteCarloStrategy {
+    fn default() -> Self {
+        Self::with_rng(StdRng::from_entropy())
+    }
+}
+
+impl MonteCarloStrategy {
+    pub fn with_rng(rng: StdRng) -> Self {
+        Self {
+            samples: 32,
+            rollout_moves: 20,
+            opponents: OpponentModel::default(),
+            time_budget: Duration::from_millis(50),
+            rng,
+        }
+    }
+
+    /// Returns every move from `board` with its average round score
+    ///
+    /// `unseen` are the cards that could be hidden and `opponents` is how many other players are
+    /// racing for the foundations. `depth` scales the samples and rollout moves, up to
+    /// [MAX_SEARCH_DEPTH] for all of them.
+    pub fn evaluate(
+        &mut self,
+        board: &Board,
+        unseen: &[CardData],
+        opponents: usize,
+        depth: u32,
+    ) -> Vec<(Decision, f32)> {
+        let depth = depth.clamp(1, MAX_SEARCH_DEPTH);
+        let max_samples = (self.samples * depth / MAX_SEARCH_DEPTH).max(1);
+        let rollout_moves = self.rollout_moves * depth / MAX_SEARCH_DEPTH;
+        let moves = board.moves();
+        let mut totals = vec![0.0; moves.len()];
+        let deadline = Instant::now() + self.time_budget;
+        let mut samples = 0;
+        while samples < max_samples && (samples == 0 || Instant::now() < deadline) {
+            let mut sample = board.clone();
+            sample.determinize(unseen, &mut self.rng);
+            // Same opponent moves for every candidate so they're compared fairly
+            let seed = self.rng.gen();
+            for (decision, total) in moves.iter().zip(totals.iter_mut()) {
+                let mut rng = StdRng::seed_from_u64(seed);
+                let mut board = sample.clone();
+                board.apply(*decision);
+                self.rollout(&mut board, opponents, rollout_moves, &mut rng);
+                *total += board.round_score();
+            }
+            samples += 1;
+        }
+        moves
+            .into_iter()
+            .zip(totals)
+            .map(|(decision, total)| (decision, total / samples as f32))
+            .collect()
+    }
+
+    /// The move with the best average round score, preferring earlier moves on a tie so drawing
+    /// comes last
+    pub fn best_move(
+        &mut self,
+        board: &Board,
+        unseen: &[CardData],
+        opponents: usize,
+        depth: u32,
+    ) -> Option<Decision> {
+        self.evaluate(board, unseen, opponents, depth)
+            .into_iter()
+            .fold(
+                None,
+                |best: Option<(Decision, f32)>, (decision, score)| match best {
+                    Some((_, best_score)) if best_score >= score => best,
+                    _ => Some((decision, score)),
+                },
+            )
+            .map(|(decision, _)| decision)
+    }
+
+    fn rollout(&self, board: &mut Board, opponents: usize, moves: u32, rng: &mut StdRng) {
+        for _ in 0..moves {
+            self.opponents.play(board, opponents, rng);
+            if board.nerts_left() == 0 {
+                break;
+            }
+            match board.greedy_move() {
+                Some(decision) => board.apply(decision),
+                None => break,
+            }
+        }
+    }
+}
+
+impl Strategy for MonteCarloStrategy {
+    fn name(&self) -> &'static str {
+        "montecarlo"
+    }
+
+    fn decide(&mut self, state: &GameState, difficulty: &DifficultySettings) -> Decision {
+        let player = state.bot_player();
+        if player.can_call_nerts {
+            return Decision::CallNerts;
+        }
+        if let Some(decision) = held_decision(state).or_else(|| stall_decision(state)) {
+            return decision;
+        }
+
+        let board = Board::from_state(state);
+        let unseen: Vec<CardData> = state
+            .accounting
+            .deck(player.steam_id)
+            .map(|d| d.candidates().collect())
+            .unwrap_or_default();
+        let opponents = state.number_playing().saturating_sub(1);
+        self.best_move(&board, &unseen, opponents, difficulty.search_depth)
+            .unwrap_or(Decision::Wait)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        state::drawpile::DrawPileModel,
+        strategy::{Source, Target},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_prefers_nerts() {
+        let board = Board {
+            nerts: vec![None, None, Some(CardData::new(Suit::Hearts, Value::Two))],
+            table: vec![vec![CardData::new(Suit::Spades, Value::Three)], Vec::new()],
+            draw: DrawPileModel::with_len(6),
+            draw_top: None,
+            can_draw: true,
+            foundations: vec![Some(CardData::new(Suit::Hearts, Value::Ace)), None],
+            contested: vec![false; 2],
+            played: 0,
+        };
+        let unseen: Vec<CardData> = [Suit::Clubs, Suit::Diamonds]
+            .into_iter()
+            .flat_map(|suit| (4..8).map(move |v| CardData::new(suit, Value::from_code(v))))
+            .collect();
+
+        let mut strategy = MonteCarloStrategy::with_rng(StdRng::seed_from_u64(1));
+        strategy.opponents = OpponentModel::Idle;
+        let scores = strategy.evaluate(&board, &unseen, 2, MAX_SEARCH_DEPTH);
+        assert_eq!(
+            strategy.best_move(&board, &unseen, 2, MAX_SEARCH_DEPTH),
+            Some(Decision::Move {
+                from: Source::Nerts,
+                to: Target::Foundation(0),
+            })
+        );
+        assert_eq!(scores.last().unwrap().0, Decision::Draw);
+    }
+}
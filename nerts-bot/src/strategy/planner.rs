@@ -0,0 +1,169 @@
+use std::time::{Duration, Instant};
+
+use crate::{difficulty::DifficultySettings, state::GameState};
+
+use super::{held_decision, sim::Board, stall_decision, Decision, Strategy};
+
+/// Searches sequences of moves for the one that gets rid of the most nerts cards
+///
+/// Looks up to [DifficultySettings::search_depth] moves ahead, one move deeper at a time until
+/// `time_budget` runs out, and plays the first move of the best sequence. Moves are only made if
+/// they lead somewhere better, otherwise it draws.
+#[derive(Debug, Clone)]
+pub struct PlannerStrategy {
+    /// Maximum time spent searching per decision
+    pub time_budget: Duration,
+}
+
+impl Default for PlannerStrategy {
+    fn default() -> Self {
+        Self {
+            time_budget: Duration::from_millis(50),
+        }
+    }
+}
+
+impl PlannerStrategy {
+    /// Returns the best first move and the score it leads to, or `None` if out of time
+    fn best_move(
+        &self,
+        board: &Board,
+        depth: u32,
+        deadline: Instant,
+    ) -> Option<Option<(Decision, f32)>> {
+        let mut best: Option<(Decision, f32)> = None;
+        for decision in board.moves() {
+            let mut next = board.clone();
+            next.apply(decision);
+            let score = Self::value(&next, depth - 1, deadline)?;
+            if best.is_none_or(|(_, best)| score > best) {
+                best = Some((decision, score));
+            }
+        }
+        Some(best)
+    }
+
+    /// Best score reachable from `board` within `depth` moves, or `None` if out of time
+    fn value(board: &Board, depth: u32, deadline: Instant) -> Option<f32> {
+        if Instant::now() > deadline {
+            return None;
+        }
+        let mut best = board.score();
+        if depth == 0 {
+            return Some(best);
+        }
+        for decision in board.moves() {
+            let mut next = board.clone();
+            next.apply(decision);
+            best = best.max(Self::value(&next, depth - 1, deadline)?);
+        }
+        Some(best)
+    }
+
+    /// Searches deeper and deeper until out of time or at `max_depth`
+    pub fn plan(&self, board: &Board, max_depth: u32) -> Option<(Decision, f32)> {
+        let deadline = Instant::now() + self.time_budget;
+        let mut best = None;
+        for depth in 1..=max_depth.max(1) {
+            match self.best_move(board, depth, deadline) {
+                Some(result) => best = result,
+                None => break,
+            }
+        }
+        best
+    }
+}
+
+impl Strategy for PlannerStrategy {
+    fn name(&self) -> &'static str {
+        "planner"
+    }
+
+    fn decide(&mut self, state: &GameState, difficulty: &DifficultySettings) -> Decision {
+        let player = state.bot_player();
+        if player.can_call_nerts {
+            return Decision::CallNerts;
+        }
+        if let Some(decision) = held_decision(state).or_else(|| stall_decision(state)) {
+            return decision;
+        }
+
+        let board = Board::from_state(state);
+        match self.plan(&board, difficulty.search_depth) {
+            Some((decision, score)) if score > board.score() => decision,
+            _ if board.can_draw() => Decision::Draw,
+            _ => Decision::Wait,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        state::{
+            card::{CardData, Suit, Value},
+            drawpile::DrawPileModel,
+        },
+        strategy::{Source, Target},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_makes_space_for_nerts() {
+        // King on the nerts pile can only go in an empty space, which the eight of clubs can make
+        let board = Board {
+            nerts: vec![None, Some(CardData::new(Suit::Spades, Value::King))],
+            table: vec![
+                vec![CardData::new(Suit::Clubs, Value::Eight)],
+                vec![CardData::new(Suit::Hearts, Value::Nine)],
+            ],
+            draw: DrawPileModel::default(),
+            draw_top: None,
+            can_draw: true,
+            foundations: vec![None; 4],
+            contested: vec![false; 4],
+            played: 0,
+        };
+        let planner = PlannerStrategy::default();
+        let (decision, score) = planner.plan(&board, 2).unwrap();
+        assert_eq!(
+            decision,
+            Decision::Move {
+                from: Source::Table(0),
+                to: Target::Table(1),
+            }
+        );
+        assert!(score > board.score());
+    }
+
+    #[test]
+    fn test_moves_stack_for_nerts() {
+        // The nine of hearts and the eight of clubs on it can go on the ten of spades together
+        let board = Board {
+            nerts: vec![None, Some(CardData::new(Suit::Spades, Value::King))],
+            table: vec![
+                vec![
+                    CardData::new(Suit::Hearts, Value::Nine),
+                    CardData::new(Suit::Clubs, Value::Eight),
+                ],
+                vec![CardData::new(Suit::Spades, Value::Ten)],
+            ],
+            draw: DrawPileModel::default(),
+            draw_top: None,
+            can_draw: true,
+            foundations: vec![None; 4],
+            contested: vec![false; 4],
+            played: 0,
+        };
+        let planner = PlannerStrategy::default();
+        let (decision, _) = planner.plan(&board, 2).unwrap();
+        assert_eq!(
+            decision,
+            Decision::Move {
+                from: Source::Stack(0, 1),
+                to: Target::Table(1),
+            }
+        );
+    }
+}
@@ -0,0 +1,405 @@
+use rand::{seq::SliceRandom, Rng};
+use steamworks::SteamId;
+
+use crate::{
+    messages::{
+        cardoutline::CardOutlineMessage,
+        player::PlayerMessage,
+        server::{GamePhase, ServerMessage},
+    },
+    position::Position,
+    state::{
+        accounting::full_deck,
+        card::{Card, CardData},
+        drawpile::DrawPileModel,
+        player::round_score,
+        GameState, NERTS_PILE_SIZE,
+    },
+};
+
+use super::{Decision, Source, Target, CONTESTED_CONFIDENCE};
+
+/// Who plays a board turned back into a game with [Board::to_state]
+const SOLO_PLAYER: u64 = 1;
+
+/// How much getting rid of a nerts card is worth compared to playing a card on a foundation
+const NERTS_WEIGHT: f32 = 10.0;
+/// Bonus for emptying the nerts pile
+const NERTS_OUT_BONUS: f32 = 50.0;
+/// Value of an empty table space while there's still a nerts pile to unblock
+const EMPTY_SPACE_WEIGHT: f32 = 0.5;
+
+/// The bot's side of the table cut down to what's needed to try out moves
+///
+/// Stacks are moved from one table space to another like in the game. Anything not known, like
+/// face down nerts cards or draw pile cards that haven't been seen, is `None` and can't be played.
+#[derive(Debug, Clone)]
+pub struct Board {
+    /// Top last
+    pub(super) nerts: Vec<Option<CardData>>,
+    /// Each stack top last
+    pub(super) table: Vec<Vec<CardData>>,
+    pub(super) draw: DrawPileModel,
+    pub(super) draw_top: Option<CardData>,
+    pub(super) can_draw: bool,
+    /// Top of each center pile
+    pub(super) foundations: Vec<Option<CardData>>,
+    /// Center piles an opponent is about to play on, which are left alone
+    pub(super) contested: Vec<bool>,
+    /// Cards played on foundations since the board was made
+    pub(super) played: u32,
+}
+
+impl Board {
+    pub fn from_state(state: &GameState) -> Self {
+        let player = state.bot_player();
+        Self {
+            nerts: player
+                .nerts_cards
+                .iter()
+                .rev()
+                .map(|c| c.visible_data())
+                .collect(),
+            table: player
+                .table
+                .iter()
+                .map(|s| {
+                    s.cards
+                        .iter()
+                        .rev()
+                        .filter_map(|c| c.visible_data())
+                        .collect()
+                })
+                .collect(),
+            draw: state.draw_pile.clone(),
+            draw_top: player.draw_pile_up.as_ref().and_then(|c| c.visible_data()),
+            can_draw: player.draw_pile_down.is_some() || player.draw_pile_up.is_some(),
+            foundations: state
+                .center_cards
+                .iter()
+                .map(|(_, c)| c.as_ref().and_then(|c| c.data))
+                .collect(),
+            contested: (0..state.center_cards.len())
+                .map(|i| state.opponents.is_contested(i, CONTESTED_CONFIDENCE))
+                .collect(),
+            played: 0,
+        }
+    }
+
+    /// Deals a shuffled deck onto an empty table with nobody else playing, for trying strategies
+    /// out without a game
+    pub fn deal(table_spaces: usize, rng: &mut impl Rng) -> Self {
+        let mut deck: Vec<CardData> = full_deck().collect();
+        deck.shuffle(rng);
+        let nerts = deck.drain(..NERTS_PILE_SIZE).map(Some).collect();
+        let table = deck.drain(..table_spaces).map(|c| vec![c]).collect();
+        let mut draw = DrawPileModel::with_len(deck.len());
+        draw.fill_unknown(&mut deck.into_iter());
+        Self {
+            nerts,
+            table,
+            draw,
+            draw_top: None,
+            can_draw: true,
+            foundations: vec![None; 4],
+            contested: vec![false; 4],
+            played: 0,
+        }
+    }
+
+    /// A round with only the bot playing, laid out like the board, for running the real
+    /// strategies on it
+    ///
+    /// A solo round has six table spaces, so the board needs them too. Card positions aren't filled
+    /// in, so decisions made on it can only be played on the board.
+    pub fn to_state(&self) -> GameState {
+        let card = |data: Option<CardData>| Card {
+            data,
+            position: Position::new(0, 0),
+            face_up: data.is_some(),
+            height: 0,
+            holder_index: None,
+        };
+        let mut state = GameState::new(SteamId::from_raw(SOLO_PLAYER));
+        state.update(&ServerMessage {
+            game_phase: GamePhase::Play,
+            player_messages: vec![PlayerMessage {
+                player_id: SOLO_PLAYER,
+                origin_x: 554,
+                origin_y: 238,
+                flipped: false,
+                is_playing: true,
+                is_ready: true,
+                can_call_nerts: self.nerts.is_empty(),
+                show_deck_button: false,
+                effects: 0,
+                card_color: 0,
+                tableau_count: self.table.len() as u8,
+                called_nerts: false,
+                nerts_cards: self.nerts.len() as u8,
+                holding_nerts_card: false,
+                points_cards: self.played as u8,
+                total_score: 0,
+                history_points: Vec::new(),
+                history_nertsed: Vec::new(),
+                ignore_disable_foundation: false,
+                cursor_x: 0,
+                cursor_y: 0,
+            }],
+            card_messages: Vec::new(),
+            card_outline_messages: (0..self.foundations.len() as i16)
+                .map(|i| CardOutlineMessage {
+                    x: 967 + 160 * i,
+                    y: 1102,
+                })
+                .collect(),
+            notification_message: None,
+            emergency_shuffle_countdown: None,
+            shuffle_count: 0,
+        });
+        for ((_, top), data) in state.center_cards.iter_mut().zip(&self.foundations) {
+            *top = data.map(|data| card(Some(data)));
+        }
+        let player = &mut state.players[0];
+        // Player piles have their top first
+        player.nerts_cards = self.nerts.iter().rev().map(|c| card(*c)).collect();
+        for (stack, cards) in player.table.iter_mut().zip(&self.table) {
+            stack.cards = cards.iter().rev().map(|c| card(Some(*c))).collect();
+        }
+        player.draw_pile_up = self.draw_top.map(|c| card(Some(c)));
+        player.draw_pile_down = self.can_draw.then(|| card(None));
+        state.draw_pile = self.draw.clone();
+        state
+    }
+
+    /// The card that would be picked up from a source
+    pub fn card(&self, source: Source) -> Option<CardData> {
+        match source {
+            Source::Nerts => self.nerts.last().copied().flatten(),
+            Source::DrawPile => self.draw_top,
+            Source::Table(i) => self.table.get(i).and_then(|s| s.last().copied()),
+            Source::Stack(i, depth) => self
+                .table
+                .get(i)
+                .and_then(|s| s.len().checked_sub(depth + 1).map(|j| s[j])),
+        }
+    }
+
+    /// First uncontested center pile `card` can go on
+    pub fn foundation_for(&self, card: CardData) -> Option<usize> {
+        self.foundations
+            .iter()
+            .zip(&self.contested)
+            .position(|(top, contested)| !contested && card.can_play_on_foundation(top.as_ref()))
+    }
+
+    pub fn nerts_left(&self) -> usize {
+        self.nerts.len()
+    }
+
+    pub fn can_draw(&self) -> bool {
+        self.can_draw
+    }
+
+    /// Every move worth trying, drawing last
+    pub fn moves(&self) -> Vec<Decision> {
+        let mut moves = Vec::new();
+        let sources = std::iter::once(Source::Nerts)
+            .chain((0..self.table.len()).map(Source::Table))
+            .chain(std::iter::once(Source::DrawPile));
+        for from in sources {
+            let card = match self.card(from) {
+                Some(card) => card,
+                None => continue,
+            };
+            if let Some(i) = self.foundation_for(card) {
+                moves.push(Decision::Move {
+                    from,
+                    to: Target::Foundation(i),
+                });
+            }
+            let mut tried_empty = false;
+            for (i, stack) in self.table.iter().enumerate() {
+                if from == Source::Table(i) {
+                    continue;
+                }
+                match stack.last() {
+                    Some(top) if card.can_play_on_table(Some(top)) => {}
+                    Some(_) => continue,
+                    // Every empty space is the same, and moving a lone card to one does nothing
+                    None if tried_empty
+                        || matches!(from, Source::Table(j) if self.table[j].len() == 1) =>
+                    {
+                        continue
+                    }
+                    None => tried_empty = true,
+                }
+                moves.push(Decision::Move {
+                    from,
+                    to: Target::Table(i),
+                });
+            }
+        }
+        for (from, stack) in self.table.iter().enumerate() {
+            for depth in 1..stack.len() {
+                let card = stack[stack.len() - 1 - depth];
+                let mut tried_empty = false;
+                for (i, onto) in self.table.iter().enumerate() {
+                    match onto.last() {
+                        _ if i == from => continue,
+                        Some(top) if card.can_play_on_table(Some(top)) => {}
+                        Some(_) => continue,
+                        // Moving a whole stack to an empty space does nothing either
+                        None if tried_empty || depth == stack.len() - 1 => continue,
+                        None => tried_empty = true,
+                    }
+                    moves.push(Decision::Move {
+                        from: Source::Stack(from, depth),
+                        to: Target::Table(i),
+                    });
+                }
+            }
+        }
+        if self.can_draw {
+            moves.push(Decision::Draw);
+        }
+        moves
+    }
+
+    /// Plays a move from [Board::moves]
+    pub fn apply(&mut self, decision: Decision) {
+        match decision {
+            Decision::Move {
+                from: Source::Stack(from, depth),
+                to: Target::Table(i),
+            } => {
+                let at = match self.table[from].len().checked_sub(depth + 1) {
+                    Some(at) => at,
+                    None => return,
+                };
+                let cards = self.table[from].split_off(at);
+                self.table[i].extend(cards);
+            }
+            Decision::Move { from, to } => {
+                let card = match self.take(from) {
+                    Some(card) => card,
+                    None => return,
+                };
+                match to {
+                    Target::Foundation(i) => {
+                        self.foundations[i] = Some(card);
+                        self.played += 1;
+                    }
+                    Target::Table(i) => self.table[i].push(card),
+                }
+            }
+            Decision::Draw => {
+                self.draw.draw();
+                self.draw_top = self.draw.top();
+            }
+            _ => {}
+        }
+    }
+
+    fn take(&mut self, source: Source) -> Option<CardData> {
+        match source {
+            Source::Nerts => self.nerts.pop().flatten(),
+            Source::Table(i) => self.table[i].pop(),
+            // Only single cards go on foundations
+            Source::Stack(..) => None,
+            Source::DrawPile => {
+                let card = self.draw_top.take();
+                self.draw.take_top();
+                self.draw_top = self.draw.top();
+                card
+            }
+        }
+    }
+
+    /// Fills in every unknown card with a random one from `unseen`, for trying out one guess at
+    /// what's hidden
+    pub fn determinize(&mut self, unseen: &[CardData], rng: &mut impl Rng) {
+        let mut unseen = unseen.to_vec();
+        unseen.shuffle(rng);
+        let mut unseen = unseen.into_iter();
+        for card in self.nerts.iter_mut().filter(|c| c.is_none()) {
+            *card = unseen.next();
+        }
+        self.draw.fill_unknown(&mut unseen);
+        if self.draw_top.is_none() {
+            self.draw_top = self.draw.top();
+        }
+    }
+
+    /// The move that improves [Board::score] the most right away, or a draw if nothing does
+    pub fn greedy_move(&self) -> Option<Decision> {
+        let score = self.score();
+        let best = self
+            .moves()
+            .into_iter()
+            .filter(|d| *d != Decision::Draw)
+            .map(|d| {
+                let mut next = self.clone();
+                next.apply(d);
+                (d, next.score())
+            })
+            .filter(|(_, s)| *s > score)
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        match best {
+            Some((decision, _)) => Some(decision),
+            None => self.can_draw.then_some(Decision::Draw),
+        }
+    }
+
+    /// Points the moves so far would get at the end of the round
+    pub fn round_score(&self) -> f32 {
+        round_score(self.played as u8, self.nerts.len() as u8) as f32
+    }
+
+    /// How good the board is, mostly from how small the nerts pile is
+    pub fn score(&self) -> f32 {
+        let mut score = self.played as f32 - NERTS_WEIGHT * self.nerts.len() as f32;
+        if self.nerts.is_empty() {
+            score += NERTS_OUT_BONUS;
+        } else {
+            let empty = self.table.iter().filter(|s| s.is_empty()).count();
+            score += EMPTY_SPACE_WEIGHT * empty as f32;
+        }
+        score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use crate::{difficulty::Difficulty, strategy};
+
+    use super::*;
+
+    #[test]
+    fn test_to_state() {
+        let mut board = Board::deal(6, &mut StdRng::seed_from_u64(1));
+        board.apply(Decision::Draw);
+        let state = board.to_state();
+        let copy = Board::from_state(&state);
+        assert_eq!(copy.nerts, board.nerts);
+        assert_eq!(copy.table, board.table);
+        assert_eq!(copy.draw_top, board.draw_top);
+        assert_eq!(copy.foundations, board.foundations);
+        assert!(copy.can_draw);
+
+        // Every strategy can play on it
+        for name in strategy::NAMES {
+            let decision = strategy::from_name(name)
+                .unwrap()
+                .decide(&state, &Difficulty::default().settings());
+            assert!(
+                matches!(decision, Decision::Move { .. } | Decision::Draw),
+                "{} decided {:?}",
+                name,
+                decision
+            );
+        }
+    }
+}
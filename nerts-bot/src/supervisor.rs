@@ -0,0 +1,249 @@
+use std::{future::Future, sync::Arc, time::Duration};
+
+use log::{error, warn};
+use tokio::{sync::watch, task::JoinHandle};
+
+use crate::{error::BotError, Result};
+
+/// What to do when a supervised task returns an error or panics
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestartPolicy {
+    /// Never restart. The first failure shuts the whole bot down
+    Never,
+    /// Restart up to `max_restarts` times, waiting `delay` before each restart
+    OnFailure { max_restarts: u32, delay: Duration },
+    /// Always restart after waiting `delay`
+    Always { delay: Duration },
+}
+
+impl RestartPolicy {
+    fn should_restart(&self, restarts: u32) -> Option<Duration> {
+        match *self {
+            RestartPolicy::Never => None,
+            RestartPolicy::OnFailure {
+                max_restarts,
+                delay,
+            } => (restarts < max_restarts).then_some(delay),
+            RestartPolicy::Always { delay } => Some(delay),
+        }
+    }
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        RestartPolicy::OnFailure {
+            max_restarts: 3,
+            delay: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Cloneable receiver for the bot's shutdown signal
+///
+/// Tasks should finish what they're doing and return `Ok(())` once this is triggered.
+#[derive(Debug, Clone)]
+pub struct ShutdownSignal(watch::Receiver<bool>);
+
+impl ShutdownSignal {
+    pub fn is_triggered(&self) -> bool {
+        *self.0.borrow()
+    }
+
+    /// Waits until shutdown is triggered
+    ///
+    /// Also returns if the supervisor has been dropped.
+    pub async fn triggered(&mut self) {
+        while !self.is_triggered() {
+            if self.0.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// Sending half of [ShutdownSignal]
+///
+/// Kept outside of the [Supervisor] so shutdown can be triggered while something is waiting on it.
+#[derive(Debug, Clone)]
+pub(crate) struct ShutdownTrigger(Arc<watch::Sender<bool>>);
+
+impl ShutdownTrigger {
+    /// Tells every task to finish
+    pub fn trigger(&self) {
+        let _ = self.0.send(true);
+    }
+}
+
+/// Spawns the bot's tasks, restarts them according to their [RestartPolicy] and collects their
+/// results
+pub(crate) struct Supervisor {
+    shutdown_tx: Arc<watch::Sender<bool>>,
+    tasks: Vec<(&'static str, JoinHandle<Result<()>>)>,
+    /// First error from a task that's been joined, kept in case [Supervisor::join] is cancelled
+    error: Option<BotError>,
+}
+
+impl Supervisor {
+    pub fn new() -> Self {
+        let (shutdown_tx, _) = watch::channel(false);
+        Self {
+            shutdown_tx: Arc::new(shutdown_tx),
+            tasks: Vec::new(),
+            error: None,
+        }
+    }
+
+    pub fn shutdown_signal(&self) -> ShutdownSignal {
+        ShutdownSignal(self.shutdown_tx.subscribe())
+    }
+
+    /// Spawns a supervised task
+    ///
+    /// `factory` is called to create the task's future, and again every time the task is restarted.
+    pub fn spawn<F, Fut>(&mut self, name: &'static str, policy: RestartPolicy, mut factory: F)
+    where
+        F: FnMut(ShutdownSignal) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        let mut shutdown = self.shutdown_signal();
+        let shutdown_trigger = self.shutdown_trigger();
+        let handle = tokio::spawn(async move {
+            let mut restarts = 0;
+            loop {
+                let error = match tokio::spawn(factory(shutdown.clone())).await {
+                    Ok(Ok(())) => return Ok(()),
+                    Ok(Err(e)) => BotError::TaskFailed {
+                        task: name,
+                        source: Box::new(e),
+                    },
+                    Err(e) if e.is_panic() => BotError::TaskPanicked(name),
+                    // Cancelled, which only happens when the runtime is shutting down
+                    Err(_) => return Ok(()),
+                };
+                if shutdown.is_triggered() {
+                    return Err(error);
+                }
+                match policy.should_restart(restarts) {
+                    Some(delay) => {
+                        restarts += 1;
+                        warn!("{}. Restarting ({})", error, restarts);
+                        tokio::select! {
+                            _ = shutdown.triggered() => return Err(error),
+                            _ = tokio::time::sleep(delay) => {}
+                        }
+                    }
+                    None => {
+                        // Bring everything else down with it
+                        error!("{}. Shutting down", error);
+                        shutdown_trigger.trigger();
+                        return Err(error);
+                    }
+                }
+            }
+        });
+        self.tasks.push((name, handle));
+    }
+
+    pub fn shutdown_trigger(&self) -> ShutdownTrigger {
+        ShutdownTrigger(self.shutdown_tx.clone())
+    }
+
+    /// Waits for every task to finish, returning the first error if any failed
+    ///
+    /// Cancel safe. Tasks are only forgotten once they've finished, so joining again carries on
+    /// waiting for the rest.
+    pub async fn join(&mut self) -> Result<()> {
+        while let Some((name, handle)) = self.tasks.first_mut() {
+            let task_result = match handle.await {
+                Ok(r) => r,
+                Err(e) if e.is_panic() => Err(BotError::TaskPanicked(name)),
+                Err(_) => Ok(()),
+            };
+            self.tasks.remove(0);
+            if let Err(e) = task_result {
+                if self.error.is_none() {
+                    self.error = Some(e);
+                } else {
+                    error!("{}", e);
+                }
+            }
+        }
+        match self.error.take() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_restart_then_fail() {
+        let mut supervisor = Supervisor::new();
+        let runs = Arc::new(AtomicU32::new(0));
+        let runs_ = runs.clone();
+        supervisor.spawn(
+            "failing",
+            RestartPolicy::OnFailure {
+                max_restarts: 2,
+                delay: Duration::ZERO,
+            },
+            move |_| {
+                runs_.fetch_add(1, Ordering::SeqCst);
+                async { Err(BotError::SendPacket) }
+            },
+        );
+        // A well behaved task should be shut down by the failing one
+        supervisor.spawn("waiting", RestartPolicy::Never, |mut shutdown| async move {
+            shutdown.triggered().await;
+            Ok(())
+        });
+
+        let result = supervisor.join().await;
+        assert!(matches!(
+            result,
+            Err(BotError::TaskFailed {
+                task: "failing",
+                ..
+            })
+        ));
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown() {
+        let mut supervisor = Supervisor::new();
+        supervisor.spawn("waiting", RestartPolicy::Never, |mut shutdown| async move {
+            shutdown.triggered().await;
+            Ok(())
+        });
+        supervisor.shutdown_trigger().trigger();
+        assert!(supervisor.join().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_join_cancelled() {
+        let mut supervisor = Supervisor::new();
+        let finished = Arc::new(AtomicU32::new(0));
+        let finished_ = finished.clone();
+        supervisor.spawn("flushing", RestartPolicy::Never, move |mut shutdown| {
+            let finished = finished_.clone();
+            async move {
+                shutdown.triggered().await;
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                finished.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }
+        });
+        let cancelled = tokio::time::timeout(Duration::from_millis(10), supervisor.join()).await;
+        assert!(cancelled.is_err());
+
+        supervisor.shutdown_trigger().trigger();
+        assert!(supervisor.join().await.is_ok());
+        assert_eq!(finished.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,150 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+};
+
+use log::debug;
+use steamworks::{CallbackHandle, P2PSessionRequest, SingleClient, SteamId};
+
+use crate::{error::BotError, Result, APP_ID};
+
+const TO_CLIENT_CHANNEL: i32 = 1;
+const TO_SERVER_CHANNEL: i32 = 2;
+
+/// How a bot sends and receives packets
+///
+/// Packets are whole messages that arrive intact and in order, like steam's P2P packets.
+pub trait Transport: Send + Sync {
+    /// Who the bot is playing as
+    fn steam_id(&self) -> SteamId;
+
+    /// Sends a packet to the server
+    fn send(&self, to: SteamId, data: &[u8]) -> Result<()>;
+
+    /// Returns the next packet and who sent it, without waiting
+    fn try_recv(&self) -> Option<(SteamId, Vec<u8>)>;
+
+    /// Called regularly so the transport can do any background work
+    fn run_callbacks(&self) {}
+
+    /// The steam client, if this goes through steam. Needed to find and join lobbies
+    fn steam_client(&self) -> Option<&steamworks::Client<steamworks::ClientManager>> {
+        None
+    }
+}
+
+/// Plays through steam as whoever is logged in
+///
+/// Steam can only be initialized once per process, so there can only be one of these.
+pub struct SteamTransport {
+    client: steamworks::Client<steamworks::ClientManager>,
+    single_client: Mutex<SingleClient>,
+    _session_req_cb: CallbackHandle,
+}
+
+impl SteamTransport {
+    pub fn init() -> Result<Self> {
+        // Login with steam
+        let (client, single_client) = steamworks::Client::init_app(APP_ID)?;
+        debug!("Connected to steam as {}", client.user().steam_id().raw());
+
+        // Accept all p2p requests
+        let client_ = client.clone();
+        let _session_req_cb = client.register_callback(move |req: P2PSessionRequest| {
+            debug!("Accepted p2p {:?}", req.remote);
+            client_.networking().accept_p2p_session(req.remote);
+        });
+
+        Ok(Self {
+            client,
+            single_client: Mutex::new(single_client),
+            _session_req_cb,
+        })
+    }
+}
+
+impl Transport for SteamTransport {
+    fn steam_id(&self) -> SteamId {
+        self.client.user().steam_id()
+    }
+
+    fn send(&self, to: SteamId, data: &[u8]) -> Result<()> {
+        let sent = self.client.networking().send_p2p_packet(
+            to,
+            steamworks::SendType::Reliable,
+            data,
+            TO_SERVER_CHANNEL,
+        );
+        if !sent {
+            return Err(BotError::SendPacket);
+        }
+        Ok(())
+    }
+
+    fn try_recv(&self) -> Option<(SteamId, Vec<u8>)> {
+        let networking = self.client.networking();
+        networking.is_p2p_packet_available()?;
+        let mut buf = vec![0u8; 0x1000];
+        let (steam_id, size) = networking.read_p2p_packet(&mut buf, TO_CLIENT_CHANNEL)?;
+        buf.truncate(size);
+        Some((steam_id, buf))
+    }
+
+    fn run_callbacks(&self) {
+        self.single_client.lock().unwrap().run_callbacks();
+    }
+
+    fn steam_client(&self) -> Option<&steamworks::Client<steamworks::ClientManager>> {
+        Some(&self.client)
+    }
+}
+
+type Inboxes = HashMap<SteamId, VecDeque<(SteamId, Vec<u8>)>>;
+
+/// Passes packets between [LocalTransport]s in the same process, for simulated games and tests
+///
+/// Any id can be used, they don't have to be real steam accounts.
+#[derive(Debug, Clone, Default)]
+pub struct LocalNetwork {
+    inboxes: Arc<Mutex<Inboxes>>,
+}
+
+impl LocalNetwork {
+    /// Connects `steam_id` to the network
+    pub fn endpoint(&self, steam_id: SteamId) -> LocalTransport {
+        self.inboxes.lock().unwrap().entry(steam_id).or_default();
+        LocalTransport {
+            steam_id,
+            network: self.clone(),
+        }
+    }
+}
+
+/// One end of a [LocalNetwork]. Can be used by a bot or to act as the server
+#[derive(Debug, Clone)]
+pub struct LocalTransport {
+    steam_id: SteamId,
+    network: LocalNetwork,
+}
+
+impl Transport for LocalTransport {
+    fn steam_id(&self) -> SteamId {
+        self.steam_id
+    }
+
+    fn send(&self, to: SteamId, data: &[u8]) -> Result<()> {
+        let mut inboxes = self.network.inboxes.lock().unwrap();
+        let inbox = inboxes.get_mut(&to).ok_or(BotError::SendPacket)?;
+        inbox.push_back((self.steam_id, data.to_vec()));
+        Ok(())
+    }
+
+    fn try_recv(&self) -> Option<(SteamId, Vec<u8>)> {
+        self.network
+            .inboxes
+            .lock()
+            .unwrap()
+            .get_mut(&self.steam_id)?
+            .pop_front()
+    }
+}
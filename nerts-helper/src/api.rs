@@ -0,0 +1,464 @@
+use std::net::{Ipv4Addr, SocketAddr};
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket},
+        Request, State, WebSocketUpgrade,
+    },
+    http::{header, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use log::info;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::broadcast::error::RecvError;
+
+use nerts_bot::{
+    agent::AgentControl,
+    events::GameEvent,
+    state::{card::Card, player::Player, GameState},
+    strategy, Bot, BotHandle,
+};
+
+/// Serves the game and controls for the bot on localhost until it fails
+///
+/// `GET /state`, `/scoreboard` and `/status` return JSON, `/snapshot` is the whole [GameState],
+/// `/metrics` is the bot's metrics for Prometheus, `/events` is a WebSocket sending every
+/// [GameEvent] as a JSON message, and `POST /pause`, `/resume`, `/strategy` and `/leave` control
+/// the bot. There's nothing to control without an agent, like when replaying, so those return 409.
+/// Requests from web pages on other sites get 403.
+pub async fn serve(
+    port: u16,
+    bot_handle: BotHandle,
+    control: Option<AgentControl>,
+) -> std::io::Result<()> {
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    info!("Serving API on http://{}", addr);
+    axum::serve(listener, router(bot_handle, control)).await
+}
+
+fn router(bot_handle: BotHandle, control: Option<AgentControl>) -> Router {
+    Router::new()
+        .route("/state", get(state))
+        .route("/snapshot", get(snapshot))
+        .route("/scoreboard", get(scoreboard))
+        .route("/status", get(status))
+        .route("/metrics", get(metrics))
+        .route("/events", get(events))
+        .route("/pause", post(pause))
+        .route("/resume", post(resume))
+        .route("/strategy", post(set_strategy))
+        .route("/leave", post(leave))
+        .layer(middleware::from_fn(local_only))
+        .with_state(Api {
+            bot_handle,
+            control,
+        })
+}
+
+/// Turns away requests from web pages that aren't on this machine
+///
+/// Only listening on localhost isn't enough, as any page open in a browser can still post a form
+/// to it or open a WebSocket to it. Browsers say which page a request is from in `Origin`, so that
+/// has to be local if it's there. `Host` has to be local too, so a page can't point its own domain
+/// here.
+async fn local_only(request: Request, next: Next) -> Response {
+    let headers = request.headers();
+    let host = match headers.get(header::HOST) {
+        Some(host) => host.to_str().ok(),
+        None => request.uri().authority().map(|a| a.as_str()),
+    };
+    let origin_local = match headers.get(header::ORIGIN) {
+        Some(origin) => origin
+            .to_str()
+            .ok()
+            .and_then(|o| o.split_once("://"))
+            .is_some_and(|(_, host)| is_local(host)),
+        None => true,
+    };
+    if !host.is_some_and(is_local) || !origin_local {
+        return error(
+            StatusCode::FORBIDDEN,
+            "Only requests from this machine are allowed",
+        );
+    }
+    next.run(request).await
+}
+
+/// Whether `host`, with or without a port, is this machine
+fn is_local(host: &str) -> bool {
+    let name = match host.strip_prefix('[') {
+        Some(rest) => rest.split(']').next(),
+        None => host.split(':').next(),
+    };
+    matches!(name, Some("localhost" | "127.0.0.1" | "::1"))
+}
+
+#[derive(Clone)]
+struct Api {
+    bot_handle: BotHandle,
+    control: Option<AgentControl>,
+}
+
+fn error(status: StatusCode, message: &str) -> Response {
+    (status, Json(json!({ "error": message }))).into_response()
+}
+
+#[derive(Serialize)]
+struct StateView {
+    phase: String,
+    phase_seconds: f32,
+    players: Vec<PlayerView>,
+    /// Top card of every center pile
+    foundations: Vec<String>,
+    shuffle_countdown: Option<u8>,
+    shuffle_count: u8,
+}
+
+#[derive(Serialize)]
+struct PlayerView {
+    steam_id: u64,
+    bot: bool,
+    ready: bool,
+    playing: bool,
+    nerts_left: u8,
+    nerts_top: Option<String>,
+    draw_pile: Option<String>,
+    /// Each stack from the bottom card up
+    table: Vec<Vec<String>>,
+    held: Vec<String>,
+    can_call_nerts: bool,
+    called_nerts: bool,
+    points_cards: u8,
+    total_score: i16,
+}
+
+#[derive(Serialize)]
+struct ScoreView {
+    steam_id: u64,
+    total_score: i16,
+    rounds: Vec<i8>,
+    /// Index of each round they called nerts in
+    nertsed: Vec<usize>,
+    present: bool,
+}
+
+#[derive(Serialize)]
+struct StatusView {
+    steam_id: u64,
+    lobby: Option<u64>,
+    connected: bool,
+    spectating: bool,
+    playing: bool,
+    paused: bool,
+    strategy: Option<&'static str>,
+}
+
+impl StateView {
+    fn new(state: &GameState) -> Self {
+        let bot_id = state.find_bot_player().map(|p| p.steam_id);
+        Self {
+            phase: format!("{:?}", state.game_phase),
+            phase_seconds: state.phase_elapsed().as_secs_f32(),
+            players: state
+                .players
+                .iter()
+                .map(|p| PlayerView::new(p, Some(p.steam_id) == bot_id))
+                .collect(),
+            foundations: state
+                .center_cards
+                .iter()
+                .filter_map(|(_, card)| card.as_ref().map(card_string))
+                .collect(),
+            shuffle_countdown: state.emergency_shuffle_countdown,
+            shuffle_count: state.shuffle_count,
+        }
+    }
+}
+
+impl PlayerView {
+    fn new(player: &Player, bot: bool) -> Self {
+        Self {
+            steam_id: player.steam_id.raw(),
+            bot,
+            ready: player.ready,
+            playing: player.playing,
+            nerts_left: player.nerts_count,
+            nerts_top: player.nerts_cards.first().map(card_string),
+            draw_pile: player.draw_pile_up.as_ref().map(card_string),
+            table: player
+                .table
+                .iter()
+                .map(|s| s.cards.iter().rev().map(card_string).collect())
+                .collect(),
+            held: player.held_cards.cards.iter().map(card_string).collect(),
+            can_call_nerts: player.can_call_nerts,
+            called_nerts: player.called_nerts,
+            points_cards: player.points_cards,
+            total_score: player.total_score,
+        }
+    }
+}
+
+impl StatusView {
+    fn new(bot: &Bot, control: Option<&AgentControl>) -> Self {
+        Self {
+            steam_id: bot.steam_id().raw(),
+            lobby: bot.lobby().map(|l| l.lobby_id().raw()),
+            connected: bot.server_id().is_some(),
+            spectating: bot.is_spectating(),
+            playing: bot.state.find_bot_player().is_some(),
+            paused: control.is_some_and(|c| c.is_paused()),
+            strategy: control.map(|c| c.strategy_name()),
+        }
+    }
+}
+
+/// Face down cards are `?`
+fn card_string(card: &Card) -> String {
+    match card.visible_data() {
+        Some(_) => card.as_small_string(),
+        None => "?".to_string(),
+    }
+}
+
+fn event_json(event: &GameEvent) -> Value {
+    match event {
+        GameEvent::PhaseChanged { from, to } => json!({
+            "type": "phase_changed",
+            "from": format!("{:?}", from),
+            "to": format!("{:?}", to),
+        }),
+        GameEvent::Notification { kind, player } => json!({
+            "type": "notification",
+            "kind": format!("{:?}", kind),
+            "player": player.map(|p| p.raw()),
+        }),
+        GameEvent::ShuffleCountdownStarted { seconds } => json!({
+            "type": "shuffle_countdown_started",
+            "seconds": seconds,
+        }),
+        GameEvent::Shuffled { count } => json!({
+            "type": "shuffled",
+            "count": count,
+        }),
+        GameEvent::Decided(decision) => json!({
+            "type": "decided",
+            "decision": format!("{:?}", decision),
+        }),
+        GameEvent::RaceLost {
+            card,
+            foundation,
+            recovery,
+        } => json!({
+            "type": "race_lost",
+            "card": format!("{}{}", card.value.as_small_str(), card.suit.as_small_str()),
+            "foundation": foundation,
+            "recovery": format!("{:?}", recovery),
+        }),
+    }
+}
+
+async fn state(State(api): State<Api>) -> Json<StateView> {
+    Json(StateView::new(&api.bot_handle.lock().await.state))
+}
+
+async fn snapshot(State(api): State<Api>) -> Json<GameState> {
+    Json(api.bot_handle.lock().await.state.clone())
+}
+
+async fn scoreboard(State(api): State<Api>) -> Json<Vec<ScoreView>> {
+    let bot = api.bot_handle.lock().await;
+    let scores = bot
+        .state
+        .scoreboard
+        .standings()
+        .into_iter()
+        .map(|e| ScoreView {
+            steam_id: e.steam_id.raw(),
+            total_score: e.total_score,
+            rounds: e.rounds.iter().map(|r| r.points).collect(),
+            nertsed: (0..e.rounds.len())
+                .filter(|&i| e.rounds[i].nertsed)
+                .collect(),
+            present: e.present,
+        })
+        .collect();
+    Json(scores)
+}
+
+async fn status(State(api): State<Api>) -> Json<StatusView> {
+    let bot = api.bot_handle.lock().await;
+    Json(StatusView::new(&bot, api.control.as_ref()))
+}
+
+async fn metrics(State(api): State<Api>) -> impl IntoResponse {
+    let text = api.bot_handle.lock().await.metrics.prometheus();
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], text)
+}
+
+async fn events(State(api): State<Api>, upgrade: WebSocketUpgrade) -> Response {
+    upgrade.on_upgrade(move |socket| send_events(socket, api.bot_handle))
+}
+
+/// Sends events until the client disconnects. Events missed from falling behind are skipped
+async fn send_events(mut socket: WebSocket, bot_handle: BotHandle) {
+    let mut events = bot_handle.subscribe_events();
+    loop {
+        tokio::select! {
+            event = events.recv() => {
+                let event = match event {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return,
+                };
+                let text = event_json(&event).to_string();
+                if socket.send(Message::Text(text.into())).await.is_err() {
+                    return;
+                }
+            }
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+async fn pause(State(api): State<Api>) -> Response {
+    let control = match api.control.as_ref() {
+        Some(control) => control,
+        None => return error(StatusCode::CONFLICT, "Nothing is playing"),
+    };
+    info!("Pausing from API");
+    control.pause();
+    status(State(api)).await.into_response()
+}
+
+async fn resume(State(api): State<Api>) -> Response {
+    let control = match api.control.as_ref() {
+        Some(control) => control,
+        None => return error(StatusCode::CONFLICT, "Nothing is playing"),
+    };
+    info!("Resuming from API");
+    control.resume();
+    status(State(api)).await.into_response()
+}
+
+#[derive(Deserialize)]
+struct StrategyRequest {
+    strategy: String,
+}
+
+async fn set_strategy(State(api): State<Api>, Json(request): Json<StrategyRequest>) -> Response {
+    let control = match api.control.as_ref() {
+        Some(control) => control,
+        None => return error(StatusCode::CONFLICT, "Nothing is playing"),
+    };
+    let strategy = match strategy::from_name(&request.strategy) {
+        Some(strategy) => strategy,
+        None => {
+            let message = format!(
+                "Unknown strategy {}, pick one of {}",
+                request.strategy,
+                strategy::NAMES.join(", ")
+            );
+            return error(StatusCode::BAD_REQUEST, &message);
+        }
+    };
+    info!("Switching to {} from API", strategy.name());
+    control.set_strategy(strategy);
+    status(State(api)).await.into_response()
+}
+
+async fn leave(State(api): State<Api>) -> Response {
+    let mut bot = api.bot_handle.lock().await;
+    if bot.server_id().is_none() {
+        return error(StatusCode::CONFLICT, "Not in a game");
+    }
+    match bot.lobby() {
+        Some(lobby) => info!("Leaving lobby {} from API", lobby.lobby_id().raw()),
+        None => info!("Leaving game from API"),
+    }
+    bot.leave_lobby();
+    Json(StatusView::new(&bot, api.control.as_ref())).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use nerts_bot::{
+        agent::Agent, difficulty::DifficultySettings, transport::LocalNetwork, BotConfig,
+    };
+    use steamworks::SteamId;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    async fn post(app: Router, path: &str, origin: Option<&str>, body: Value) -> StatusCode {
+        let mut request = axum::http::Request::post(path)
+            .header(header::HOST, "localhost:7878")
+            .header(header::CONTENT_TYPE, "application/json");
+        if let Some(origin) = origin {
+            request = request.header(header::ORIGIN, origin);
+        }
+        let request = request.body(Body::from(body.to_string())).unwrap();
+        app.oneshot(request).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn test_controls() {
+        let network = LocalNetwork::default();
+        let bot_handle =
+            Bot::start_with(network.endpoint(SteamId::from_raw(1)), BotConfig::default())
+                .await
+                .unwrap();
+        let replaying = router(bot_handle.clone(), None);
+        assert_eq!(
+            post(replaying, "/pause", None, Value::Null).await,
+            StatusCode::CONFLICT
+        );
+
+        let agent = Agent::new(
+            strategy::from_name("greedy").unwrap(),
+            DifficultySettings::default(),
+        );
+        let app = router(bot_handle.clone(), Some(agent.control()));
+        let unknown = json!({ "strategy": "random" });
+        assert_eq!(
+            post(app.clone(), "/strategy", None, unknown).await,
+            StatusCode::BAD_REQUEST
+        );
+        let planner = json!({ "strategy": "planner" });
+        assert_eq!(
+            post(
+                app.clone(),
+                "/strategy",
+                Some("http://localhost:3000"),
+                planner
+            )
+            .await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            post(app, "/pause", Some("https://example.com"), Value::Null).await,
+            StatusCode::FORBIDDEN
+        );
+        assert!(!agent.control().is_paused());
+
+        bot_handle.shutdown().await.unwrap();
+    }
+
+    #[test]
+    fn test_event_json() {
+        let event = GameEvent::Shuffled { count: 2 };
+        assert_eq!(
+            event_json(&event).to_string(),
+            r#"{"count":2,"type":"shuffled"}"#
+        );
+    }
+}
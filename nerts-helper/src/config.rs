@@ -0,0 +1,174 @@
+use std::path::{Path, PathBuf};
+
+use clap::{Args, Parser, Subcommand};
+use nerts_bot::cosmetics::{CardBack, CardColor};
+use serde::Deserialize;
+
+/// Read for defaults if it exists, anything given on the command line takes priority
+pub const DEFAULT_CONFIG_PATH: &str = "nerts-helper.toml";
+
+/// A bot for NERTS! Online
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// Config file to read defaults from
+    #[arg(long, global = true, default_value = DEFAULT_CONFIG_PATH)]
+    pub config: PathBuf,
+
+    #[command(flatten)]
+    pub options: Options,
+
+    /// Joins the first friend's lobby if not given
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum Command {
+    /// List every lobby that can be joined
+    ListLobbies,
+    /// Join a lobby and play
+    Join {
+        /// Lobby id, or `friend` for the first lobby a friend is in
+        #[arg(default_value = "friend")]
+        lobby: String,
+    },
+    /// Join a lobby without ever playing, only following the game
+    Spectate {
+        /// Lobby id, or `friend` for the first lobby a friend is in
+        #[arg(default_value = "friend")]
+        lobby: String,
+    },
+    /// Play back a game recorded with --record
+    Replay { file: PathBuf },
+    /// Play solo deals offline with the strategy and report how it did
+    Simulate {
+        #[arg(long, default_value_t = 10)]
+        rounds: u32,
+    },
+    /// Print how the bot has done from its match history
+    Report,
+}
+
+/// Settings that can be given on the command line or in the config file
+#[derive(Debug, Clone, Default, Args, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Options {
+    /// greedy, planner or montecarlo
+    #[arg(long, global = true)]
+    pub strategy: Option<String>,
+    /// beginner, casual, skilled or unfair
+    #[arg(long, global = true)]
+    pub difficulty: Option<String>,
+    #[arg(
+        long,
+        global = true,
+        help = format!(
+            "Card back to use from 0 to {}, saved for the account",
+            CardBack::COUNT - 1
+        )
+    )]
+    pub card_back: Option<u8>,
+    #[arg(
+        long,
+        global = true,
+        help = format!(
+            "Card colour to use from 0 to {}, saved for the account",
+            CardColor::COUNT - 1
+        )
+    )]
+    pub card_color: Option<u8>,
+    /// Use the card colour of whoever is on the same side of the table, saved for the account.
+    /// `--match-team=false` turns it off
+    #[arg(
+        long,
+        global = true,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true"
+    )]
+    pub match_team: Option<bool>,
+    /// error, warn, info, debug or trace. Overridden by RUST_LOG
+    #[arg(long, global = true)]
+    pub log_level: Option<String>,
+    /// Log to this file as well
+    #[arg(long, global = true)]
+    pub log_file: Option<PathBuf>,
+    /// Record every packet from the server to this file, for replay
+    #[arg(long, global = true)]
+    pub record: Option<PathBuf>,
+    /// Print the game every second instead of showing it full screen. `--no-tui=false` shows it
+    /// again if the config file turned it off
+    #[arg(
+        long,
+        global = true,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true"
+    )]
+    pub no_tui: Option<bool>,
+    /// Serve the game state and controls as JSON on localhost at this port
+    #[arg(long, global = true)]
+    pub api_port: Option<u16>,
+}
+
+impl Options {
+    /// Reads options from a toml file. A missing file is only an error if `required`
+    pub fn load(path: &Path, required: bool) -> Result<Self, String> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if !required && e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Self::default())
+            }
+            Err(e) => return Err(format!("Couldn't read {}: {}", path.display(), e)),
+        };
+        toml::from_str(&contents).map_err(|e| format!("Couldn't parse {}: {}", path.display(), e))
+    }
+
+    /// Fills in anything not set with `defaults`
+    pub fn or(self, defaults: Options) -> Self {
+        Self {
+            strategy: self.strategy.or(defaults.strategy),
+            difficulty: self.difficulty.or(defaults.difficulty),
+            card_back: self.card_back.or(defaults.card_back),
+            card_color: self.card_color.or(defaults.card_color),
+            match_team: self.match_team.or(defaults.match_team),
+            log_level: self.log_level.or(defaults.log_level),
+            log_file: self.log_file.or(defaults.log_file),
+            record: self.record.or(defaults.record),
+            no_tui: self.no_tui.or(defaults.no_tui),
+            api_port: self.api_port.or(defaults.api_port),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_line_first() {
+        let cli = Cli::parse_from(["nerts-helper", "--strategy", "planner", "join", "123"]);
+        assert!(matches!(cli.command, Some(Command::Join { ref lobby }) if lobby == "123"));
+
+        let config: Options = toml::from_str(
+            r#"
+            strategy = "greedy"
+            difficulty = "casual"
+            match-team = true
+            "#,
+        )
+        .unwrap();
+        let options = cli.options.or(config.clone());
+        assert_eq!(options.strategy.as_deref(), Some("planner"));
+        assert_eq!(options.difficulty.as_deref(), Some("casual"));
+        assert_eq!(options.match_team, Some(true));
+
+        let cli = Cli::parse_from(["nerts-helper", "--match-team=false", "--no-tui"]);
+        let options = cli.options.or(config);
+        assert_eq!(options.match_team, Some(false));
+        assert_eq!(options.no_tui, Some(true));
+
+        assert!(toml::from_str::<Options>("strategy = 1").is_err());
+    }
+}
@@ -1,309 +1,509 @@
-use std::{io::Write, time::Duration};
+use std::{
+    collections::BTreeMap,
+    io::IsTerminal,
+    path::Path,
+    process::exit,
+    time::{Duration, UNIX_EPOCH},
+};
 
-use flexi_logger::Logger;
-use log::info;
+use clap::Parser;
+use flexi_logger::{FileSpec, Logger};
+use log::{error, info};
 
 use nerts_bot::{
+    agent::{Agent, AgentControl},
+    cosmetics::{CardBack, CardColor, Look, LookStore},
+    difficulty::{Difficulty, DifficultySettings},
+    events::GameEvent,
+    history::{MatchHistory, RoundRecord, Summary},
     lobbyinfo::LobbyInfo,
     messages::server::GamePhase,
-    position::Position,
+    recording::{self, Recording},
     state::{
-        card::{Card, Suit, Value},
+        card::{Card, Suit},
+        phase::PhaseView,
         GameState,
     },
-    Bot,
+    strategy::{self, sim::Board, Decision},
+    Bot, BotConfig, BotHandle,
 };
 use rand::prelude::*;
-use tokio::time::Instant;
+use tokio::sync::broadcast;
+
+use config::{Cli, Command, Options, DEFAULT_CONFIG_PATH};
+use tui::LogBuffer;
+
+/// Where finished rounds are recorded
+const HISTORY_PATH: &str = "nerts-history.tsv";
+/// Where each bot account's card look is kept
+const LOOKS_PATH: &str = "nerts-looks.tsv";
+/// Moves each simulated deal is played for before giving up
+const SIMULATE_MOVES: u32 = 300;
+/// How often a metrics summary is logged while playing
+const METRICS_INTERVAL: Duration = Duration::from_secs(60);
+
+mod api;
+mod config;
+mod tui;
 
 #[tokio::main]
 async fn main() {
-    // Start logger
-    Logger::try_with_env_or_str("debug")
-        .unwrap()
-        .start()
-        .unwrap();
+    let cli = Cli::parse();
+    let required = cli.config != Path::new(DEFAULT_CONFIG_PATH);
+    let options = match Options::load(&cli.config, required) {
+        Ok(config) => cli.options.or(config),
+        Err(e) => fail(e),
+    };
+    let command = cli.command.unwrap_or(Command::Join {
+        lobby: "friend".to_string(),
+    });
+
+    // Check everything before starting anything
+    let strategy = options.strategy.as_deref().unwrap_or("greedy");
+    if strategy::from_name(strategy).is_none() {
+        fail(format!(
+            "Unknown strategy {}, pick one of {}",
+            strategy,
+            strategy::NAMES.join(", ")
+        ));
+    }
+    let difficulty = match options.difficulty.as_deref() {
+        Some(name) => Difficulty::from_name(name)
+            .unwrap_or_else(|| fail(format!("Unknown difficulty {}", name))),
+        None => Difficulty::default(),
+    };
 
-    // Create rng
-    let mut rng = ThreadRng::default();
+    // Show the game full screen if there's a terminal to show it in, logging into it rather than
+    // over it
+    let showing_game = !matches!(
+        command,
+        Command::ListLobbies | Command::Report | Command::Simulate { .. }
+    );
+    let no_tui = options.no_tui.unwrap_or(false);
+    let logs =
+        (showing_game && !no_tui && std::io::stdout().is_terminal()).then(LogBuffer::default);
+    start_logger(&options, logs.clone());
+
+    match command {
+        Command::Report => return report(&MatchHistory::new(HISTORY_PATH)),
+        Command::Simulate { rounds } => return simulate(strategy, &difficulty.settings(), rounds),
+        _ => {}
+    }
 
     // Create bot
-    let bot_handle = Bot::start().await.unwrap();
-
-    // println!("Fetching lobbies...");
-    // for lobby in bot.lobbies().await.unwrap().iter() {
-    //     match lobby {
-    //         LobbyInfo::SteamLobby(id) => print!("Public {} ", id.raw()),
-    //         LobbyInfo::FriendLobby(id, lobby_id) => {
-    //             print!("Friend {} {} ", id.steamid32(), lobby_id.raw())
-    //         }
-    //     }
-    //     let max_string = lobby
-    //         .member_limit(&bot)
-    //         .map_or_else(|| "N/A".to_string(), |a| a.to_string());
-    //     println!("{}/{}", lobby.member_count(&bot), max_string);
-    // }
-
-    // Find a friends lobby
-    info!("Finding friend lobby");
-    std::io::stdout().flush().unwrap();
-    let friend_lobby = loop {
-        let lobbies = bot_handle.lock().await.lobbies().await.unwrap();
-        let lobby = lobbies
-            .into_iter()
-            .find(|l| matches!(l, LobbyInfo::FriendLobby(_, _)));
-        if let Some(lobby) = lobby {
-            break lobby;
+    let config = BotConfig {
+        spectate: matches!(command, Command::Spectate { .. }),
+        record: options.record.clone(),
+        ..BotConfig::default()
+    };
+    let started = match &command {
+        Command::Replay { file } => match Recording::read(file) {
+            Ok(recording) => recording::replay(recording, config).await,
+            Err(e) => fail(format!("Couldn't read {}: {}", file.display(), e)),
+        },
+        _ => Bot::start(config).await,
+    };
+    let bot_handle = started.unwrap_or_else(|e| fail(format!("Couldn't start bot: {}", e)));
+
+    // Run until the bot fails, ctrl-c is pressed or the command finishes
+    let run = async {
+        match &command {
+            Command::ListLobbies => list_lobbies(&bot_handle).await,
+            Command::Join { lobby } | Command::Spectate { lobby } => {
+                if !join(&bot_handle, lobby).await {
+                    return;
+                }
+                let spectating = bot_handle.lock().await.is_spectating();
+                if !spectating {
+                    set_look(&bot_handle, &options).await;
+                }
+                let mut agent = Agent::new(
+                    strategy::from_name(strategy).unwrap(),
+                    difficulty.settings(),
+                );
+                agent.set_history(MatchHistory::new(HISTORY_PATH));
+                let control = (!spectating).then(|| agent.control());
+                let play = async {
+                    tokio::join!(
+                        agent.run(&bot_handle),
+                        log_events(&bot_handle),
+                        log_metrics(&bot_handle)
+                    )
+                };
+                tokio::select! {
+                    _ = play => {}
+                    _ = show(&bot_handle, logs) => {}
+                    _ = serve_api(options.api_port, &bot_handle, control) => {}
+                }
+            }
+            Command::Replay { .. } => {
+                tokio::select! {
+                    _ = log_events(&bot_handle) => {}
+                    _ = show(&bot_handle, logs) => {}
+                    _ = serve_api(options.api_port, &bot_handle, None) => {}
+                }
+            }
+            Command::Simulate { .. } | Command::Report => unreachable!(),
         }
-        // Sleep if unsuccessful
-        tokio::time::sleep(Duration::from_millis(500)).await;
     };
-
-    // Join the lobby
-    info!("Joining lobby {:?}", friend_lobby.lobby_id());
-    bot_handle
-        .lock()
-        .await
-        .join_lobby(friend_lobby)
-        .await
-        .unwrap();
-
-    // Main loop
-    // Can get stuck as sometimes the wait_until conditions don't happen and there are no timeouts
-    // Every loop the bot tries to do one of the following, starting from the top:
-    //   - If not in game, click ready and wait for game to start
-    //   - Call nerts
-    //   - If holding a card play it if possible, drop it if not
-    //   - Find a card that can be played and pick it up
-    //   - If there's an empty space on the table play a card from the nerts pile on it
-    //   - If nothing else, draw from the deck
-    bot_handle.lock().await.state.send_make_ready = true;
-    bot_handle.wait_until(|bot| bot.state.initialized).await;
-    let mut last = Instant::now();
-    'main: loop {
-        // Get a lock on the bot
-        let mut bot = bot_handle.lock().await;
-
-        // Draw the game to console if it's been more than a second since the last time
-        // Won't happen while stuck in a wait_until so could be improved
-        let curr_instant = Instant::now();
-        if curr_instant.duration_since(last).as_millis() > 1000 {
-            last = curr_instant;
-            draw_game(&bot.state);
+    tokio::select! {
+        _ = run => {}
+        result = bot_handle.join() => {
+            if let Err(e) = result {
+                error!("Bot stopped: {}", e);
+            }
+            return;
         }
+        _ = tokio::signal::ctrl_c() => info!("Shutting down"),
+    }
+    if let Err(e) = bot_handle.shutdown().await {
+        error!("Error while shutting down: {}", e);
+    }
+}
 
-        // *shrugs*
-        if bot.state.players.is_empty() {
-            drop(bot);
-            bot_handle
-                .wait_until(|bot| !bot.state.players.is_empty())
-                .await;
-            continue;
+/// Prints why the helper can't carry on and exits
+fn fail(message: String) -> ! {
+    eprintln!("{}", message);
+    exit(2);
+}
+
+/// Logs to `logs` if showing the game full screen, otherwise to stderr, and to a file if asked
+fn start_logger(options: &Options, logs: Option<LogBuffer>) {
+    let level = options.log_level.as_deref().unwrap_or("debug");
+    let logger = Logger::try_with_env_or_str(level)
+        .unwrap_or_else(|e| fail(format!("Bad log level {}: {}", level, e)));
+    let file = options.log_file.as_ref().map(|path| {
+        FileSpec::try_from(path)
+            .unwrap_or_else(|e| fail(format!("Bad log file {}: {}", path.display(), e)))
+    });
+    let logger = match (file, logs) {
+        (Some(file), Some(logs)) => logger.log_to_file_and_writer(file, Box::new(logs)),
+        (Some(file), None) => logger.log_to_file(file),
+        (None, Some(logs)) => logger.log_to_writer(Box::new(logs)),
+        (None, None) => logger,
+    };
+    if let Err(e) = logger.start() {
+        fail(format!("Couldn't start logging: {}", e));
+    }
+}
+
+async fn list_lobbies(bot_handle: &BotHandle) {
+    let bot = bot_handle.lock().await;
+    let lobbies = match bot.lobbies().await {
+        Ok(lobbies) => lobbies,
+        Err(e) => return error!("Couldn't fetch lobbies: {}", e),
+    };
+    if lobbies.is_empty() {
+        println!("No lobbies found");
+    }
+    for lobby in lobbies.iter() {
+        match lobby {
+            LobbyInfo::SteamLobby(id) => print!("Public {:<20} ", id.raw()),
+            LobbyInfo::FriendLobby(id, lobby_id) => {
+                print!("Friend {:<20} {} ", lobby_id.raw(), id.steamid32())
+            }
         }
+        let limit = lobby
+            .member_limit(&bot)
+            .map_or_else(|| "N/A".to_string(), |a| a.to_string());
+        let count = lobby.member_count(&bot).unwrap_or(0);
+        println!("{}/{}", count, limit);
+    }
+}
 
-        match bot.state.game_phase {
-            GamePhase::Lobby | GamePhase::Nerts => {
-                // Send ready if not ready
-                info!("Waiting for game to start");
-                if !bot.state.bot_player().ready {
-                    bot.state.send_make_ready = true;
-                    bot.send_client_message().await;
+/// Joins the lobby with id `lobby`, or the first friend's lobby if `lobby` is `friend`. Returns
+/// false if it couldn't
+async fn join(bot_handle: &BotHandle, lobby: &str) -> bool {
+    let lobby_info = if lobby.eq_ignore_ascii_case("friend") {
+        // Wait for a friend to be in a lobby
+        info!("Finding friend lobby");
+        loop {
+            let lobbies = match bot_handle.lock().await.lobbies().await {
+                Ok(lobbies) => lobbies,
+                Err(e) => {
+                    error!("Couldn't fetch lobbies: {}", e);
+                    return false;
                 }
-                drop(bot);
-                // Wait until playing
-                bot_handle
-                    .wait_until(|bot| bot.state.game_phase == GamePhase::Play)
-                    .await;
+            };
+            let lobby = lobbies
+                .into_iter()
+                .find(|l| matches!(l, LobbyInfo::FriendLobby(_, _)));
+            if let Some(lobby) = lobby {
+                break lobby;
             }
-            GamePhase::Intro => {
-                // Send ready if not ready
-                // Might have mixed up the phases slightly but it works so
-                info!("Waiting for intro to end");
-                if !bot.state.bot_player().ready {
-                    bot.state.send_make_ready = true;
-                    bot.send_client_message().await;
-                }
-                drop(bot);
-                // Wait until playing
-                bot_handle
-                    .wait_until(|bot| bot.state.game_phase == GamePhase::Play)
-                    .await;
+            // Sleep if unsuccessful
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+    } else {
+        let id: u64 = match lobby.parse() {
+            Ok(id) => id,
+            Err(_) => {
+                error!("Lobby should be an id or `friend`, not {}", lobby);
+                return false;
             }
-            GamePhase::Play => {
-                let bot_player = bot.state.bot_player();
-
-                // Call nerts if possible
-                if bot_player.can_call_nerts {
-                    info!("Calling nerts");
-                    bot.state.send_make_ready = true;
-                    bot.send_client_message().await;
-                    drop(bot);
-                    bot_handle
-                        .wait_until(|bot| !bot.state.bot_player().can_call_nerts)
-                        .await;
-                    continue;
-                }
+        };
+        let lobbies = match bot_handle.lock().await.lobbies().await {
+            Ok(lobbies) => lobbies,
+            Err(e) => {
+                error!("Couldn't fetch lobbies: {}", e);
+                return false;
+            }
+        };
+        match lobbies.into_iter().find(|l| l.lobby_id().raw() == id) {
+            Some(lobby) => lobby,
+            None => {
+                error!("Couldn't find lobby {}", id);
+                return false;
+            }
+        }
+    };
+
+    info!("Joining lobby {:?}", lobby_info.lobby_id());
+    if let Err(e) = bot_handle.lock().await.join_lobby(lobby_info).await {
+        error!("Couldn't join lobby: {}", e);
+        return false;
+    }
+    true
+}
+
+/// Keeps the same look on this account, picking a random one the first time and saving any
+/// given in `options`
+async fn set_look(bot_handle: &BotHandle, options: &Options) {
+    let mut bot = bot_handle.lock().await;
+    let store = LookStore::new(LOOKS_PATH);
+    let steam_id = bot.steam_id();
+    let saved = match store.load(steam_id) {
+        Ok(look) => look,
+        Err(e) => {
+            error!("Couldn't read {}: {}", store.path().display(), e);
+            None
+        }
+    };
+    let mut look = saved.unwrap_or_else(|| Look::random(&mut StdRng::from_entropy()));
+    if let Some(code) = options.card_back {
+        match CardBack::from_code(code) {
+            Some(back) => look.back = back,
+            None => error!(
+                "No card back {}, pick one from 0 to {}",
+                code,
+                CardBack::COUNT - 1
+            ),
+        }
+    }
+    if let Some(code) = options.card_color {
+        match CardColor::from_code(code) {
+            Some(color) => look.color = color,
+            None => error!(
+                "No card colour {}, pick one from 0 to {}",
+                code,
+                CardColor::COUNT - 1
+            ),
+        }
+    }
+    if let Some(match_team) = options.match_team {
+        look.match_team = match_team;
+    }
+
+    if saved != Some(look) {
+        info!(
+            "Using card back {} in colour {} from now on",
+            look.back.code(),
+            look.color.code()
+        );
+        if let Err(e) = store.save(steam_id, look) {
+            error!("Couldn't save look: {}", e);
+        }
+    }
+    bot.state.target_look = look;
+}
+
+/// Serves the API if there's a port for it. Never returns, even if the API fails, so the game
+/// carries on without it
+async fn serve_api(port: Option<u16>, bot_handle: &BotHandle, control: Option<AgentControl>) {
+    if let Some(port) = port {
+        if let Err(e) = api::serve(port, bot_handle.clone(), control).await {
+            error!("API stopped: {}", e);
+        }
+    }
+    std::future::pending().await
+}
+
+/// Shows the game until it's quit, full screen if there's somewhere to log to, otherwise
+/// drawing it to console every second
+async fn show(bot_handle: &BotHandle, logs: Option<LogBuffer>) {
+    match logs {
+        Some(logs) => {
+            if let Err(e) = tui::run(bot_handle, logs).await {
+                error!("Terminal UI failed: {}", e);
+            }
+        }
+        None => loop {
+            tokio::time::sleep(Duration::from_millis(1000)).await;
+            let bot = bot_handle.lock().await;
+            if bot.state.game_phase == GamePhase::Play {
+                draw_game(&bot.state);
+            }
+        },
+    }
+}
+
+/// Plays solo deals offline with the strategy and prints how it did
+///
+/// Nobody else is playing, so nothing is ever contested, and every decision takes as long as it
+/// would in a game.
+fn simulate(strategy: &str, difficulty: &DifficultySettings, rounds: u32) {
+    let mut rng = StdRng::from_entropy();
+    let mut strategy = strategy::from_name(strategy).unwrap();
+
+    let mut points = 0.0;
+    let mut out = 0;
+    for _ in 0..rounds {
+        // Solo rounds have six table spaces
+        let mut board = Board::deal(6, &mut rng);
+        for _ in 0..SIMULATE_MOVES {
+            match strategy.decide(&board.to_state(), difficulty) {
+                decision @ (Decision::Move { .. } | Decision::Draw) => board.apply(decision),
+                // Called nerts or stuck
+                _ => break,
+            }
+        }
+        points += board.round_score();
+        out += (board.nerts_left() == 0) as u32;
+    }
+    println!(
+        "{} over {} rounds: {:.1} average points, went out {:.1}% of the time",
+        strategy.name(),
+        rounds,
+        points / rounds.max(1) as f32,
+        out as f32 / rounds.max(1) as f32 * 100.0
+    );
+}
 
-                // If holding card either play it or drop it
-                if !bot_player.held_cards.cards.is_empty() {
-                    // Should never be holding a stack
-                    assert!(bot_player.held_cards.cards.len() == 1);
-                    let held_card = bot_player.held_cards.cards.first().unwrap();
-
-                    // Check if we can play on any of the center cards
-                    let play_on = bot.state.center_cards.iter().find(|(_, c)| match c {
-                        Some(c) => held_card.can_play_on(c),
-                        None => held_card.data.as_ref().unwrap().value == Value::Ace,
-                    });
-                    if let Some((pos, card)) = play_on {
-                        // Play card
-                        let card_str = match card {
-                            Some(card) => card.as_small_string(),
-                            None => "_".to_string(),
-                        };
-                        info!("Playing {} on {}", held_card.as_small_string(), card_str);
-                        let new_pos =
-                            *pos + Position::new(rng.gen_range(10..50), rng.gen_range(10..80));
-                        assert_ne!(bot.state.target_cursor_pos, new_pos);
-                        bot.state.target_cursor_pos = new_pos;
-                        bot.state.send_left_click = true;
-                        bot.send_client_message().await;
-
-                        // Wait until mouse moved
-                        drop(bot);
-                        bot_handle
-                            .wait_until(|bot| bot.state.bot_player().cursor == new_pos)
-                            .await;
-                        continue;
-                    } else {
-                        // Drop card
+/// Logs a summary of the bot's metrics every so often
+async fn log_metrics(bot_handle: &BotHandle) {
+    let mut last = bot_handle.lock().await.metrics.clone();
+    loop {
+        tokio::time::sleep(METRICS_INTERVAL).await;
+        let metrics = bot_handle.lock().await.metrics.clone();
+        info!("{}", metrics.summary_since(&last, METRICS_INTERVAL));
+        last = metrics;
+    }
+}
+
+/// Logs what's happening in the game
+async fn log_events(bot_handle: &BotHandle) {
+    let mut events = bot_handle.subscribe_events();
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => return,
+        };
+        match event {
+            GameEvent::PhaseChanged { to, .. } => {
+                info!("Now in {:?}", to);
+                let bot = bot_handle.lock().await;
+                if let PhaseView::Nerts(results) = bot.state.phase_view() {
+                    for outcome in results.outcomes.iter() {
                         info!(
-                            "Couldn't play {}. Dropping card",
-                            held_card.as_small_string()
+                            "{:<17} {:>3} points, {} nerts cards left",
+                            outcome.steam_id.raw(),
+                            outcome.points(),
+                            outcome.nerts_left
                         );
-                        bot.state.send_right_click = true;
-                        bot.send_client_message().await;
-
-                        // Wait until not holding card
-                        drop(bot);
-                        bot_handle
-                            .wait_until(|bot| bot.state.bot_player().held_cards.cards.is_empty())
-                            .await;
-                        continue;
                     }
                 }
+            }
+            GameEvent::Notification { kind, player } => {
+                let who = player.map_or_else(|| "nobody".to_string(), |p| p.raw().to_string());
+                info!("Notification {:?} about {}", kind, who);
+            }
+            GameEvent::ShuffleCountdownStarted { seconds } => {
+                info!("Nobody's playing, shuffling in {}s", seconds)
+            }
+            GameEvent::Shuffled { count } => info!("Shuffle number {}", count),
+            // Already logged by the agent
+            GameEvent::Decided(_) | GameEvent::RaceLost { .. } => {}
+        }
+    }
+}
 
-                // Find a card we can play and pick it up
-                let mut playable = Vec::new();
-                if !bot_player.nerts_cards.is_empty() {
-                    playable.push(bot_player.nerts_cards.first().unwrap());
-                }
-                for stack in bot_player.table.iter() {
-                    if !stack.cards.is_empty() {
-                        playable.push(stack.cards.first().unwrap());
-                    }
-                }
-                if bot_player.draw_pile_up.is_some() {
-                    playable.push(bot_player.draw_pile_up.as_ref().unwrap());
-                }
-                for card in playable {
-                    if !card.face_up || card.data.is_none() {
-                        continue;
-                    }
-                    let play_on = bot.state.center_cards.iter().find(|(_, c)| match c {
-                        Some(c) => card.can_play_on(c),
-                        None => card.data.as_ref().unwrap().value == Value::Ace,
-                    });
-                    if play_on.is_none() {
-                        continue;
-                    }
-                    let (_, play_on) = play_on.unwrap();
-                    let play_on_str = match play_on {
-                        Some(c) => c.as_small_string(),
-                        None => "_".to_string(),
-                    };
-                    info!(
-                        "Picking up {} to play on {}",
-                        card.as_small_string(),
-                        play_on_str,
-                    );
-
-                    let new_pos =
-                        card.position + Position::new(rng.gen_range(10..50), rng.gen_range(10..80));
-                    assert_ne!(bot.state.target_cursor_pos, new_pos);
-                    bot.state.target_cursor_pos = new_pos;
-                    bot.state.send_left_click = true;
-                    bot.send_client_message().await;
-
-                    // Wait until mouse moved
-                    drop(bot);
-                    bot_handle
-                        .wait_until(|bot| bot.state.bot_player().cursor == new_pos)
-                        .await;
-                    continue 'main;
-                }
+/// Prints win rates overall, for each strategy and for each week
+fn report(history: &MatchHistory) {
+    let records = match history.records() {
+        Ok(records) => records,
+        Err(e) => {
+            error!("Couldn't read {}: {}", history.path().display(), e);
+            return;
+        }
+    };
+    if records.is_empty() {
+        println!("No rounds recorded in {}", history.path().display());
+        return;
+    }
 
-                // If possible, play from nerts pile onto table
-                if !bot_player.nerts_cards.is_empty() {
-                    let empty_table = bot_player
-                        .table
-                        .iter()
-                        .enumerate()
-                        .find(|(_, s)| s.cards.is_empty());
-                    if let Some((table_index, _)) = empty_table {
-                        info!("Playing nerts card onto empty table space");
-                        let table_pos = bot_player.table_base_positions()[table_index];
-                        let nerts_card_pos = bot_player.nerts_cards.first().unwrap().position;
-
-                        let new_pos = nerts_card_pos
-                            + Position::new(rng.gen_range(10..50), rng.gen_range(10..80));
-                        assert_ne!(bot.state.target_cursor_pos, new_pos);
-                        bot.state.target_cursor_pos = new_pos;
-                        bot.state.send_left_click = true;
-                        bot.send_client_message().await;
-
-                        // Wait until mouse moved
-                        drop(bot);
-                        bot_handle
-                            .wait_until(|bot| bot.state.bot_player().cursor == new_pos)
-                            .await;
-
-                        let mut bot = bot_handle.lock().await;
-                        let new_pos =
-                            table_pos + Position::new(rng.gen_range(10..50), rng.gen_range(10..80));
-                        assert_ne!(bot.state.target_cursor_pos, new_pos);
-                        bot.state.target_cursor_pos = new_pos;
-                        bot.state.send_left_click = true;
-                        bot.send_client_message().await;
-
-                        // Wait until mouse moved
-                        drop(bot);
-                        bot_handle
-                            .wait_until(|bot| bot.state.bot_player().cursor == new_pos)
-                            .await;
-
-                        continue;
-                    }
-                }
+    println!(
+        "{:<20} {:>6} {:>6} {:>6} {:>8}",
+        "", "Rounds", "Win %", "Nerts", "Avg pts"
+    );
+    print_summary("All", &Summary::from_records(&records));
 
-                // If we can do nothing else, draw
-                // Also randomise card color and back and mouse position for the hell of it
-                let new_pos = Position::new(rng.gen_range(200..3800), rng.gen_range(200..2200));
-                bot.state.send_draw = true;
-                bot.state.target_card_back = rng.gen_range(0..12);
-                bot.state.target_card_color = rng.gen_range(0..12);
-                bot.state.target_cursor_pos = new_pos;
-                bot.send_client_message().await;
-                drop(bot);
-                bot_handle
-                    .wait_until(|bot| {
-                        bot.state.bot_player().cursor == new_pos
-                            || bot.state.bot_player().can_call_nerts
-                    })
-                    .await;
-            }
-        }
+    println!();
+    let mut by_strategy: BTreeMap<&str, Vec<&RoundRecord>> = BTreeMap::new();
+    for record in records.iter() {
+        by_strategy
+            .entry(&record.strategy)
+            .or_default()
+            .push(record);
     }
+    for (strategy, records) in by_strategy {
+        print_summary(strategy, &Summary::from_records(records));
+    }
+
+    println!();
+    let mut by_week: BTreeMap<i64, Vec<&RoundRecord>> = BTreeMap::new();
+    for record in records.iter() {
+        let days = record
+            .ended_at
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as i64
+            / 86400;
+        // The epoch was a Thursday, weeks start on Monday
+        let week_start = days - (days + 3).rem_euclid(7);
+        by_week.entry(week_start).or_default().push(record);
+    }
+    for (week_start, records) in by_week {
+        let (year, month, day) = civil_from_days(week_start);
+        let label = format!("Week of {}-{:02}-{:02}", year, month, day);
+        print_summary(&label, &Summary::from_records(records));
+    }
+}
+
+fn print_summary(label: &str, summary: &Summary) {
+    println!(
+        "{:<20} {:>6} {:>5.1}% {:>6} {:>8.1}",
+        label,
+        summary.rounds,
+        summary.win_rate() * 100.0,
+        summary.nerts_called,
+        summary.average_points()
+    );
+}
+
+/// Converts days since the unix epoch to a year, month and day
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    // From http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    (year, month, day)
 }
 
 fn draw_game(state: &GameState) {
@@ -373,10 +573,19 @@ const RESET: &str = "\x1b[0m";
 
 #[cfg(test)]
 mod tests {
-    use nerts_bot::state::card::CardData;
+    use nerts_bot::{
+        position::Position,
+        state::card::{CardData, Value},
+    };
 
     use super::*;
 
+    #[test]
+    fn test_civil_from_days() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(19_723), (2024, 1, 1));
+    }
+
     #[test]
     fn test_draw_card() {
         println!(
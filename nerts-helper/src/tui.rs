@@ -0,0 +1,390 @@
+use std::{
+    collections::VecDeque,
+    io,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use flexi_logger::{writers::LogWriter, DeferredNow};
+use log::Record;
+use nerts_bot::{
+    events::GameEvent,
+    state::{
+        card::{Card, Suit},
+        phase::PhaseView,
+        player::Player,
+        GameState,
+    },
+    BotHandle,
+};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Paragraph},
+    Frame,
+};
+use tokio::sync::broadcast::error::TryRecvError;
+
+/// Log lines kept for the log pane
+const LOG_LINES: usize = 200;
+/// Events kept for the events pane
+const RECENT_EVENTS: usize = 50;
+
+/// Keeps the last few log lines so they can be shown in the TUI instead of printed over it
+#[derive(Debug, Clone, Default)]
+pub struct LogBuffer(Arc<Mutex<VecDeque<String>>>);
+
+impl LogBuffer {
+    fn tail(&self, lines: usize) -> Vec<String> {
+        let buffer = self.0.lock().unwrap();
+        buffer
+            .iter()
+            .skip(buffer.len().saturating_sub(lines))
+            .cloned()
+            .collect()
+    }
+}
+
+impl LogWriter for LogBuffer {
+    fn write(&self, _now: &mut DeferredNow, record: &Record) -> io::Result<()> {
+        let mut buffer = self.0.lock().unwrap();
+        if buffer.len() == LOG_LINES {
+            buffer.pop_front();
+        }
+        buffer.push_back(format!("{:<5} {}", record.level(), record.args()));
+        Ok(())
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Shows the game full screen, redrawing every time the state changes, until q is pressed
+pub async fn run(bot_handle: &BotHandle, logs: LogBuffer) -> io::Result<()> {
+    let mut terminal = ratatui::init();
+    let mut events = bot_handle.subscribe_events();
+    let mut recent = VecDeque::new();
+    let result = loop {
+        loop {
+            match events.try_recv() {
+                Ok(event) => {
+                    if recent.len() == RECENT_EVENTS {
+                        recent.pop_front();
+                    }
+                    recent.push_back(event);
+                }
+                Err(TryRecvError::Lagged(_)) => continue,
+                Err(_) => break,
+            }
+        }
+
+        let bot = bot_handle.lock().await;
+        let drawn = terminal.draw(|frame| draw(frame, &bot.state, &recent, &logs));
+        drop(bot);
+        if let Err(e) = drawn {
+            break Err(e);
+        }
+
+        match quit_pressed() {
+            Ok(false) => {}
+            Ok(true) => break Ok(()),
+            Err(e) => break Err(e),
+        }
+        tokio::select! {
+            _ = bot_handle.wait_for_update() => {}
+            _ = tokio::time::sleep(Duration::from_millis(100)) => {}
+        }
+    };
+    ratatui::restore();
+    result
+}
+
+/// Reads every waiting key press, returning true if any of them were q or ctrl-c
+fn quit_pressed() -> io::Result<bool> {
+    let mut quit = false;
+    while event::poll(Duration::ZERO)? {
+        if let Event::Key(key) = event::read()? {
+            quit |= key.kind == KeyEventKind::Press
+                && (key.code == KeyCode::Char('q')
+                    || (key.code == KeyCode::Char('c')
+                        && key.modifiers.contains(KeyModifiers::CONTROL)));
+        }
+    }
+    Ok(quit)
+}
+
+fn draw(frame: &mut Frame, state: &GameState, recent: &VecDeque<GameEvent>, logs: &LogBuffer) {
+    let [header, body] =
+        Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(frame.area());
+    let [game, side] =
+        Layout::horizontal([Constraint::Percentage(65), Constraint::Percentage(35)]).areas(body);
+    let [events_area, logs_area] =
+        Layout::vertical([Constraint::Percentage(40), Constraint::Percentage(60)]).areas(side);
+
+    frame.render_widget(Paragraph::new(header_line(state)), header);
+    match state.phase_view() {
+        PhaseView::Play => draw_play(frame, game, state),
+        view => draw_between_rounds(frame, game, state, view),
+    }
+
+    let lines: Vec<Line> = recent
+        .iter()
+        .rev()
+        .take(events_area.height as usize)
+        .map(|e| Line::raw(format!("{:?}", e)))
+        .collect();
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title("Events")),
+        events_area,
+    );
+
+    let lines: Vec<Line> = logs
+        .tail(logs_area.height.saturating_sub(2) as usize)
+        .into_iter()
+        .map(Line::raw)
+        .collect();
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title("Log")),
+        logs_area,
+    );
+}
+
+fn header_line(state: &GameState) -> Line<'static> {
+    let mut spans = vec![Span::styled(
+        format!(" {:?} ", state.game_phase),
+        Style::new().add_modifier(Modifier::REVERSED),
+    )];
+    spans.push(Span::raw(format!(
+        "  {}s in phase",
+        state.phase_elapsed().as_secs()
+    )));
+    if let Some(seconds) = state.emergency_shuffle_countdown {
+        spans.push(Span::styled(
+            format!("  Shuffling in {}s", seconds),
+            Style::new().fg(Color::Yellow),
+        ));
+    }
+    if state.shuffle_count > 0 {
+        spans.push(Span::raw(format!("  Shuffles: {}", state.shuffle_count)));
+    }
+    if let Some(notification) = state.notification {
+        let who = notification
+            .player
+            .map_or_else(|| "?".to_string(), |p| p.raw().to_string());
+        spans.push(Span::raw(format!("  {:?} ({})", notification.kind, who)));
+    }
+    spans.push(Span::raw("  q to quit"));
+    Line::from(spans)
+}
+
+fn draw_play(frame: &mut Frame, area: Rect, state: &GameState) {
+    let playing: Vec<&Player> = state.players.iter().filter(|p| p.playing).collect();
+    let mut constraints = vec![Constraint::Length(6)];
+    constraints.extend(playing.iter().map(|p| Constraint::Length(player_height(p))));
+    constraints.push(Constraint::Min(0));
+    let areas = Layout::vertical(constraints).split(area);
+
+    frame.render_widget(
+        Paragraph::new(foundation_lines(state)).block(Block::bordered().title("Foundations")),
+        areas[0],
+    );
+    for (player, area) in playing.into_iter().zip(areas.iter().skip(1)) {
+        let is_bot = state
+            .find_bot_player()
+            .is_some_and(|b| b.steam_id == player.steam_id);
+        draw_player(frame, *area, player, is_bot);
+    }
+}
+
+/// Every foundation's top card, one line per suit
+fn foundation_lines(state: &GameState) -> Vec<Line<'static>> {
+    let tops: Vec<&Card> = state
+        .center_cards
+        .iter()
+        .filter_map(|(_, c)| c.as_ref())
+        .collect();
+    let mut lines: Vec<Line> = [Suit::Spades, Suit::Hearts, Suit::Clubs, Suit::Diamonds]
+        .into_iter()
+        .map(|suit| {
+            let mut spans = vec![Span::raw(format!("{} ", suit_symbol(suit)))];
+            for card in tops
+                .iter()
+                .filter(|c| c.data.is_some_and(|d| d.suit == suit))
+            {
+                spans.push(card_span(Some(card)));
+                spans.push(Span::raw(" "));
+            }
+            Line::from(spans)
+        })
+        .collect();
+    let empty = state.center_cards.len() - tops.len();
+    if let Some(line) = lines.last_mut() {
+        line.push_span(Span::raw(format!("   ({} empty)", empty)));
+    }
+    lines
+}
+
+/// Borders, a summary line and the tallest table stack
+fn player_height(player: &Player) -> u16 {
+    let tallest = player
+        .table
+        .iter()
+        .map(|s| s.cards.len())
+        .max()
+        .unwrap_or(0);
+    3 + tallest.max(1) as u16
+}
+
+fn draw_player(frame: &mut Frame, area: Rect, player: &Player, is_bot: bool) {
+    let title = format!(
+        "{}{}  score {}  round {:+}  cursor {},{}",
+        player.steam_id.raw(),
+        if is_bot { " (bot)" } else { "" },
+        player.total_score,
+        player.projected_round_score(),
+        player.cursor.x,
+        player.cursor.y
+    );
+
+    let mut summary = vec![
+        Span::raw(format!("Nerts {:>2}: ", player.nerts_count)),
+        card_span(player.nerts_cards.first()),
+        Span::raw("  Draw: "),
+        card_span(player.draw_pile_down.as_ref()),
+        Span::raw(" "),
+        card_span(player.draw_pile_up.as_ref()),
+    ];
+    if !player.held_cards.cards.is_empty() {
+        summary.push(Span::raw("  Holding: "));
+        // Bottom of the held stack first
+        for card in player.held_cards.cards.iter().rev() {
+            summary.push(card_span(Some(card)));
+            summary.push(Span::raw(" "));
+        }
+    }
+    if player.can_call_nerts {
+        summary.push(Span::styled("  NERTS!", Style::new().fg(Color::Green)));
+    }
+    let mut lines = vec![Line::from(summary)];
+
+    // Each table stack as a column, bottom card first like in game
+    let tallest = player
+        .table
+        .iter()
+        .map(|s| s.cards.len())
+        .max()
+        .unwrap_or(0);
+    for row in 0..tallest.max(1) {
+        let mut spans = vec![Span::raw("Table:    ")];
+        for stack in player.table.iter() {
+            let card = stack
+                .cards
+                .len()
+                .checked_sub(row + 1)
+                .map(|i| &stack.cards[i]);
+            spans.push(match card {
+                Some(card) => card_span(Some(card)),
+                None if row == 0 => card_span(None),
+                None => Span::raw("   "),
+            });
+            spans.push(Span::raw(" "));
+        }
+        if row > 0 {
+            spans[0] = Span::raw("          ");
+        }
+        lines.push(Line::from(spans));
+    }
+
+    let style = if is_bot {
+        Style::new().fg(Color::Cyan)
+    } else {
+        Style::new()
+    };
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title(title).border_style(style)),
+        area,
+    );
+}
+
+fn draw_between_rounds(frame: &mut Frame, area: Rect, state: &GameState, view: PhaseView) {
+    let lines: Vec<Line> = match view {
+        PhaseView::Lobby(lobby) => lobby
+            .members
+            .iter()
+            .map(|p| {
+                Line::raw(format!(
+                    "{:<17} {:<8} {:<5} score {}",
+                    p.steam_id.raw(),
+                    if p.playing { "playing" } else { "watching" },
+                    if p.ready { "ready" } else { "" },
+                    p.total_score
+                ))
+            })
+            .collect(),
+        PhaseView::Intro { elapsed, remaining } => match remaining {
+            Some(remaining) => vec![Line::raw(format!("Dealing in {}s", remaining.as_secs()))],
+            None => vec![Line::raw(format!("Dealing in... ({}s)", elapsed.as_secs()))],
+        },
+        PhaseView::Nerts(results) => results
+            .outcomes
+            .iter()
+            .map(|o| {
+                Line::raw(format!(
+                    "{:<17} {:>+4} points  {:>2} played  {:>2} left{}",
+                    o.steam_id.raw(),
+                    o.points(),
+                    o.points_cards,
+                    o.nerts_left,
+                    if o.called_nerts { "  NERTS" } else { "" }
+                ))
+            })
+            .collect(),
+        PhaseView::Play => Vec::new(),
+    };
+    let mut lines = lines;
+    lines.push(Line::raw(""));
+    lines.push(Line::raw("Standings"));
+    for entry in state.scoreboard.standings() {
+        lines.push(Line::raw(format!(
+            "{:<17} {:>4}",
+            entry.steam_id.raw(),
+            entry.total_score
+        )));
+    }
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title(format!("{:?}", state.game_phase))),
+        area,
+    );
+}
+
+fn suit_symbol(suit: Suit) -> &'static str {
+    match suit {
+        Suit::Clubs => "♣",
+        Suit::Diamonds => "♦",
+        Suit::Hearts => "♥",
+        Suit::Spades => "♠",
+    }
+}
+
+/// A card three characters wide, face down cards hatched and missing cards as a gap
+fn card_span(card: Option<&Card>) -> Span<'static> {
+    let data = match card {
+        None => return Span::styled(" __", Style::new().fg(Color::DarkGray)),
+        Some(card) => match card.visible_data() {
+            Some(data) => data,
+            None => return Span::styled("▒▒▒", Style::new().fg(Color::Blue)),
+        },
+    };
+    let fg = if data.suit.is_red() {
+        Color::Red
+    } else {
+        Color::Black
+    };
+    Span::styled(
+        format!("{:>2}{}", data.value.as_small_str(), suit_symbol(data.suit)),
+        Style::new().fg(fg).bg(Color::White),
+    )
+}